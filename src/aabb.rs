@@ -0,0 +1,70 @@
+use crate::ray::Ray;
+use crate::vec3::Point;
+
+//axis-aligned bounding box
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Aabb {
+    pub min: Point,
+    pub max: Point,
+}
+impl Aabb {
+    pub fn new(min: Point, max: Point) -> Self {
+        Self { min, max }
+    }
+
+    //the parametric interval over which the ray is inside the box, via the slab method
+    pub fn intersect(&self, r: Ray, t_min: f64, t_max: f64) -> Option<(f64, f64)> {
+        let mut t_min = t_min;
+        let mut t_max = t_max;
+
+        for axis in 0..3 {
+            let inv_d = r.direction.e[axis].recip();
+            let mut t0 = (self.min.e[axis] - r.origin.e[axis]) * inv_d;
+            let mut t1 = (self.max.e[axis] - r.origin.e[axis]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            t_min = if t0 > t_min { t0 } else { t_min };
+            t_max = if t1 < t_max { t1 } else { t_max };
+            //flat boxes, such as around axis-aligned triangles, still have a single point of contact
+            if t_max < t_min {
+                return None;
+            }
+        }
+
+        Some((t_min, t_max))
+    }
+
+    pub fn size(&self) -> Point {
+        self.max - self.min
+    }
+
+    pub fn centroid(&self) -> Point {
+        0.5 * (self.min + self.max)
+    }
+
+    //the smallest box containing both boxes
+    pub fn surrounding(a: Self, b: Self) -> Self {
+        Self::new(
+            Point::new(
+                a.min.e[0].min(b.min.e[0]),
+                a.min.e[1].min(b.min.e[1]),
+                a.min.e[2].min(b.min.e[2]),
+            ),
+            Point::new(
+                a.max.e[0].max(b.max.e[0]),
+                a.max.e[1].max(b.max.e[1]),
+                a.max.e[2].max(b.max.e[2]),
+            ),
+        )
+    }
+
+    //the smallest box containing all the points, if there are any
+    pub fn from_points<I: IntoIterator<Item = Point>>(points: I) -> Option<Self> {
+        points
+            .into_iter()
+            .map(|p| Self::new(p, p))
+            .reduce(Self::surrounding)
+    }
+}
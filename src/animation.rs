@@ -0,0 +1,278 @@
+//cameras that move over time, for fly-throughs and turntables rendered with `Renderer::sequence`
+
+use std::ops::{Add, Mul, Sub};
+
+use crate::{aabb::Aabb, ray::Camera, Point, Vec3};
+
+//where the camera is and how its lens is set at one moment
+#[derive(Clone, Copy, Debug)]
+pub struct Keyframe {
+    pub time: f64, //seconds
+    pub origin: Point,
+    pub look_at: Point,
+    pub vfov: f64,
+    pub focus_dist: f64,
+    pub aperture: f64,
+}
+
+//how the camera moves between keyframes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+    Linear,     //straight lines at constant speed, turning sharply at each key
+    CatmullRom, //a smooth curve through every key
+    Bezier, //one smooth curve from the first key to the last, pulled towards the others; ignores their times
+}
+
+#[derive(Clone)]
+pub struct CameraPath {
+    keys: Vec<Keyframe>,
+    interpolation: Interpolation,
+    vup: Vec3,
+    template: Camera, //everything about the camera that the keys don't set
+    closed: bool,
+}
+impl CameraPath {
+    pub fn new(mut keys: Vec<Keyframe>, interpolation: Interpolation, aspect_ratio: f64) -> Self {
+        assert!(
+            !keys.is_empty(),
+            "a camera path needs at least one keyframe"
+        );
+        keys.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Self {
+            keys,
+            interpolation,
+            vup: Vec3::new(0.0, 1.0, 0.0),
+            template: Camera::new(
+                Point::default(),
+                Point::new(0.0, 0.0, -1.0),
+                Vec3::new(0.0, 1.0, 0.0),
+                90.0,
+                aspect_ratio,
+                0.0,
+                1.0,
+            ),
+            closed: false,
+        }
+    }
+
+    //a loop whose last key is the first one again, so splines carry on smoothly through the seam rather than
+    //slowing into it
+    pub fn closed(mut self) -> Self {
+        self.closed = true;
+        self
+    }
+
+    //every frame takes its projection, aperture shape, exposure and the rest from `camera`, and only its
+    //position, view, field of view, aperture size and focus from the keys
+    pub fn with_camera(mut self, camera: Camera) -> Self {
+        self.template = camera;
+        self
+    }
+
+    pub fn with_up(mut self, vup: Vec3) -> Self {
+        self.vup = vup;
+        self
+    }
+
+    pub fn start(&self) -> f64 {
+        self.keys[0].time
+    }
+
+    pub fn end(&self) -> f64 {
+        self.keys[self.keys.len() - 1].time
+    }
+
+    //the camera's settings at any time, held at the first or last key outside the path
+    pub fn at(&self, time: f64) -> Keyframe {
+        Keyframe {
+            time,
+            origin: self.interpolate(time, |key| key.origin),
+            look_at: self.interpolate(time, |key| key.look_at),
+            vfov: self.interpolate(time, |key| key.vfov),
+            focus_dist: self.interpolate(time, |key| key.focus_dist),
+            aperture: self.interpolate(time, |key| key.aperture),
+        }
+    }
+
+    pub fn camera(&self, time: f64) -> Camera {
+        let key = self.at(time);
+        self.template.clone().with_view(
+            key.origin,
+            key.look_at,
+            self.vup,
+            key.vfov,
+            key.aperture,
+            key.focus_dist,
+        )
+    }
+
+    //a camera for every frame from the start up to, but not including, the end; the end is where the frame
+    //after the last begins, so looping paths don't show the same view twice
+    pub fn frames(&self, fps: f64) -> impl Iterator<Item = Camera> + '_ {
+        let count = ((self.end() - self.start()) * fps - 1e-9).ceil().max(1.0) as usize;
+        (0..count).map(move |frame| self.camera(self.start() + frame as f64 / fps))
+    }
+
+    fn interpolate<T, F>(&self, time: f64, value: F) -> T
+    where
+        T: Copy + Add<Output = T> + Sub<Output = T> + Mul<f64, Output = T>,
+        F: Fn(&Keyframe) -> T,
+    {
+        let keys = &self.keys;
+        let last = keys.len() - 1;
+        if time <= keys[0].time || last == 0 {
+            return value(&keys[0]);
+        }
+        if time >= keys[last].time {
+            return value(&keys[last]);
+        }
+
+        if self.interpolation == Interpolation::Bezier {
+            //de Casteljau's algorithm, with the keys as control points
+            let s = (time - keys[0].time) / (keys[last].time - keys[0].time);
+            let mut points: Vec<T> = keys.iter().map(&value).collect();
+            for level in (1..=last).rev() {
+                for i in 0..level {
+                    points[i] = points[i] * (1.0 - s) + points[i + 1] * s;
+                }
+            }
+            return points[0];
+        }
+
+        let i = keys.partition_point(|key| key.time <= time) - 1;
+        let (t0, t1) = (keys[i].time, keys[i + 1].time);
+        let (p0, p1) = (value(&keys[i]), value(&keys[i + 1]));
+        let s = (time - t0) / (t1 - t0);
+        match self.interpolation {
+            Interpolation::Linear | Interpolation::Bezier => p0 * (1.0 - s) + p1 * s,
+            Interpolation::CatmullRom => {
+                //rates of change from the neighbouring keys, so uneven spacing doesn't overshoot
+                let slope = |j: usize| {
+                    if self.closed && last >= 2 && (j == 0 || j == last) {
+                        //the keys either side of the seam, one from each end
+                        let span =
+                            (keys[1].time - keys[0].time) + (keys[last].time - keys[last - 1].time);
+                        return (value(&keys[1]) - value(&keys[last - 1])) * (1.0 / span);
+                    }
+                    let (before, after) = (j.saturating_sub(1), (j + 1).min(last));
+                    (value(&keys[after]) - value(&keys[before]))
+                        * (1.0 / (keys[after].time - keys[before].time))
+                };
+                let (m0, m1) = (slope(i) * (t1 - t0), slope(i + 1) * (t1 - t0));
+                //cubic Hermite basis
+                let (s2, s3) = (s * s, s * s * s);
+                p0 * (2.0 * s3 - 3.0 * s2 + 1.0)
+                    + m0 * (s3 - 2.0 * s2 + s)
+                    + p1 * (3.0 * s2 - 2.0 * s3)
+                    + m1 * (s3 - s2)
+            }
+        }
+    }
+}
+
+//one turn around a scene's bounds in `seconds`, looking down on them from a little above, far enough away
+//that they stay in frame all the way round
+pub fn turntable(bounds: Aabb, aspect_ratio: f64, seconds: f64) -> CameraPath {
+    const VFOV: f64 = 40.0;
+    const ELEVATION: f64 = 20.0;
+    const STEPS: usize = 36;
+
+    let centre = 0.5 * (bounds.min + bounds.max);
+    let radius = 0.5 * (bounds.max - bounds.min).length();
+    //fit the bounding sphere into the narrower of the two fields of view
+    let tangent = (VFOV.to_radians() / 2.0).tan();
+    let half_fov = tangent.min(tangent * aspect_ratio);
+    let distance = radius * (1.0 + half_fov * half_fov).sqrt() / half_fov;
+
+    let elevation = ELEVATION.to_radians();
+    let keys = (0..=STEPS)
+        .map(|step| {
+            let angle = 2.0 * std::f64::consts::PI * step as f64 / STEPS as f64;
+            let offset = Vec3::new(
+                elevation.cos() * angle.sin(),
+                elevation.sin(),
+                elevation.cos() * angle.cos(),
+            );
+            Keyframe {
+                time: seconds * step as f64 / STEPS as f64,
+                origin: centre + distance * offset,
+                look_at: centre,
+                vfov: VFOV,
+                focus_dist: distance,
+                aperture: 0.0,
+            }
+        })
+        .collect();
+    CameraPath::new(keys, Interpolation::CatmullRom, aspect_ratio).closed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{material::Colour, ray::Projection};
+
+    #[test]
+    fn frames_keep_the_template_camera_settings() {
+        let key = |time: f64, x: f64| Keyframe {
+            time,
+            origin: Point::new(x, 0.0, 5.0),
+            look_at: Point::default(),
+            vfov: 40.0,
+            focus_dist: 5.0,
+            aperture: 0.0,
+        };
+        let template = Camera::new(
+            Point::default(),
+            Point::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            2.0,
+            0.0,
+            1.0,
+        )
+        .with_projection(Projection::Orthographic(2.0))
+        .with_exposure(100.0, 1.0 / 125.0, 16.0)
+        .with_vignetting(1.0)
+        .with_white_balance(3200.0);
+        let path = CameraPath::new(
+            vec![key(0.0, -2.0), key(1.0, 2.0)],
+            Interpolation::Linear,
+            2.0,
+        )
+        .with_camera(template.clone());
+        let camera = path.camera(0.5);
+
+        //exposure, white balance and vignetting develop light the same way from the new view
+        let white = Colour::new(1.0, 1.0, 1.0);
+        for (s, t) in [(0.5, 0.5), (0.0, 0.0), (1.0, 0.3)] {
+            let expected = template.develop(white, s, t);
+            let developed = camera.develop(white, s, t);
+            assert!((developed - expected).length() < 1e-12);
+        }
+        //and an orthographic view stays parallel
+        let corner = camera.get_ray(0.0, 0.0).unwrap().direction.unit();
+        let centre = camera.get_ray(0.5, 0.5).unwrap().direction.unit();
+        assert!((corner - centre).length() < 1e-12);
+        assert!((centre - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-12);
+    }
+
+    #[test]
+    fn turntables_circle_smoothly_through_the_seam() {
+        let bounds = Aabb::new(Point::new(-1.0, 0.0, -1.0), Point::new(1.0, 2.0, 1.0));
+        let path = turntable(bounds, 16.0 / 9.0, 6.0);
+        let centre = Point::new(0.0, 1.0, 0.0);
+        let radius = (path.at(0.0).origin - centre).length();
+
+        //every frame stays the same distance from the middle
+        for frame in 0..600 {
+            let origin = path.at(6.0 * frame as f64 / 600.0).origin;
+            assert!(((origin - centre).length() - radius).abs() < 1e-4 * radius);
+        }
+
+        //and the camera's velocity just after the start matches that just before the end
+        let h = 1e-4;
+        let start = (path.at(h).origin - path.at(0.0).origin) / h;
+        let end = (path.at(6.0).origin - path.at(6.0 - h).origin) / h;
+        assert!((start - end).length() < 1e-3 * start.length());
+    }
+}
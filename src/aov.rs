@@ -0,0 +1,186 @@
+//what each pixel's camera rays first hit, for compositing and denoising, alongside the beauty pass
+
+use std::{
+    fs::File,
+    io::{self, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use crate::{exr, material::Colour, Point, Vec3};
+
+//a pass's channels, by name
+type Channels = Vec<(&'static str, Vec<f32>)>;
+
+//one value per pixel, top row first like `Renderer::frame`. Each pixel averages the same camera rays as the
+//beauty pass, counting misses as zero, so edges blend the same way; IDs can't blend, so they take the value
+//most of the rays agree on
+pub struct Aovs {
+    pub width: usize,
+    pub height: usize,
+    pub beauty: Vec<Colour>, //exposed but not tone mapped
+    pub albedo: Vec<Colour>,
+    pub normal: Vec<Vec3>, //the shading normal, facing the camera
+    pub depth: Vec<f64>,   //see `Camera::depth`
+    pub position: Vec<Point>,
+    pub uv: Vec<(f64, f64)>,
+    pub object_id: Vec<Option<usize>>, //the scene graph node, as `Renderer::pick` gives
+    pub material_id: Vec<Option<usize>>, //as `Material::with_id` numbers them
+}
+
+//the parts of a camera ray's first hit that the passes keep
+pub(crate) struct Sample {
+    pub albedo: Colour,
+    pub normal: Vec3,
+    pub depth: f64,
+    pub position: Point,
+    pub uv: (f64, f64),
+    pub object_id: Option<usize>,
+    pub material_id: Option<usize>,
+}
+
+impl Aovs {
+    pub(crate) fn new(width: usize, height: usize) -> Self {
+        let area = width * height;
+        Self {
+            width,
+            height,
+            beauty: Vec::with_capacity(area),
+            albedo: Vec::with_capacity(area),
+            normal: Vec::with_capacity(area),
+            depth: Vec::with_capacity(area),
+            position: Vec::with_capacity(area),
+            uv: Vec::with_capacity(area),
+            object_id: Vec::with_capacity(area),
+            material_id: Vec::with_capacity(area),
+        }
+    }
+
+    //the next pixel, from the first hits of its camera rays (None for misses) out of the `count` it was
+    //meant to have; projections that don't reach a pixel give it fewer
+    pub(crate) fn push(&mut self, beauty: Colour, hits: &[Option<Sample>], count: usize) {
+        let count = count as f64;
+        let mean = |value: &dyn Fn(&Sample) -> Vec3| {
+            hits.iter()
+                .flatten()
+                .map(value)
+                .fold(Vec3::default(), |a, b| a + b)
+                / count
+        };
+        self.beauty.push(beauty);
+        self.albedo.push(mean(&|hit| hit.albedo));
+        self.normal.push(mean(&|hit| hit.normal));
+        self.position.push(mean(&|hit| hit.position));
+        self.depth
+            .push(hits.iter().flatten().map(|hit| hit.depth).sum::<f64>() / count);
+        let uv = mean(&|hit| Vec3::new(hit.uv.0, hit.uv.1, 0.0));
+        self.uv.push((uv.e[0], uv.e[1]));
+        self.object_id
+            .push(most_common(hits.iter().map(|hit| hit.as_ref()?.object_id)));
+        self.material_id.push(most_common(
+            hits.iter().map(|hit| hit.as_ref()?.material_id),
+        ));
+    }
+
+    //every pass as a layer of one EXR: the beauty as plain R, G and B, the rest prefixed with their names
+    pub fn write_exr<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let channels: Vec<_> = self
+            .layers()
+            .into_iter()
+            .flat_map(|(layer, channels)| {
+                channels
+                    .into_iter()
+                    .map(move |(channel, values)| match layer {
+                        "beauty" => (channel.to_string(), values),
+                        _ => (format!("{}.{}", layer, channel), values),
+                    })
+            })
+            .collect();
+        exr::write(
+            BufWriter::new(File::create(path)?),
+            self.width,
+            self.height,
+            &channels,
+        )
+    }
+
+    //each pass as its own EXR in `directory`, named `<prefix><pass>.exr`; the paths written
+    pub fn write_separate<P: AsRef<Path>>(
+        &self,
+        directory: P,
+        prefix: &str,
+    ) -> io::Result<Vec<PathBuf>> {
+        let mut paths = vec![];
+        for (layer, channels) in self.layers() {
+            let path = directory.as_ref().join(format!("{}{}.exr", prefix, layer));
+            let channels: Vec<_> = channels
+                .into_iter()
+                .map(|(channel, values)| (channel.to_string(), values))
+                .collect();
+            exr::write(
+                BufWriter::new(File::create(&path)?),
+                self.width,
+                self.height,
+                &channels,
+            )?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+
+    //each pass's name and channels; IDs are stored as floats, with -1 for nothing
+    fn layers(&self) -> Vec<(&'static str, Channels)> {
+        let split = |values: &[Vec3], names: [&'static str; 3]| {
+            (0..3)
+                .map(|axis| {
+                    (
+                        names[axis],
+                        values.iter().map(|v| v.e[axis] as f32).collect(),
+                    )
+                })
+                .collect()
+        };
+        let id = |ids: &[Option<usize>]| {
+            vec![(
+                "ID",
+                ids.iter()
+                    .map(|id| id.map_or(-1.0, |id| id as f32))
+                    .collect(),
+            )]
+        };
+        vec![
+            ("beauty", split(&self.beauty, ["R", "G", "B"])),
+            ("albedo", split(&self.albedo, ["R", "G", "B"])),
+            ("normal", split(&self.normal, ["X", "Y", "Z"])),
+            (
+                "depth",
+                vec![("Z", self.depth.iter().map(|&d| d as f32).collect())],
+            ),
+            ("position", split(&self.position, ["X", "Y", "Z"])),
+            (
+                "uv",
+                vec![
+                    ("U", self.uv.iter().map(|uv| uv.0 as f32).collect()),
+                    ("V", self.uv.iter().map(|uv| uv.1 as f32).collect()),
+                ],
+            ),
+            ("object_id", id(&self.object_id)),
+            ("material_id", id(&self.material_id)),
+        ]
+    }
+}
+
+//the value that turns up most, the earliest of any tied; None if there are none at all
+fn most_common(values: impl Iterator<Item = Option<usize>>) -> Option<usize> {
+    let values: Vec<_> = values.collect();
+    let count = |value: &Option<usize>| values.iter().filter(|&v| v == value).count();
+    let mut best = None;
+    let mut best_count = 0;
+    for value in &values {
+        let n = count(value);
+        if n > best_count {
+            best = *value;
+            best_count = n;
+        }
+    }
+    best
+}
@@ -0,0 +1,105 @@
+use crate::{aabb::Aabb, ray::Ray};
+
+const MAX_LEAF_SIZE: usize = 4;
+
+//bounding volume hierarchy over primitives identified by their index, shared by scenes and meshes
+#[derive(Clone, Default)]
+pub struct Bvh {
+    nodes: Vec<Node>,
+    order: Vec<usize>, //primitive indices, grouped so every leaf's primitives are contiguous
+}
+
+#[derive(Clone, Copy)]
+struct Node {
+    bounds: Aabb,
+    start: usize, //first primitive for a leaf, the second child for a branch (the first follows its parent)
+    count: usize, //zero for branches
+}
+
+impl Bvh {
+    pub fn new(boxes: &[Aabb]) -> Self {
+        let mut bvh = Self {
+            nodes: Vec::with_capacity(2 * boxes.len()),
+            order: (0..boxes.len()).collect(),
+        };
+        if !boxes.is_empty() {
+            bvh.build(boxes, 0, boxes.len());
+        }
+        bvh
+    }
+
+    pub fn bounds(&self) -> Option<Aabb> {
+        self.nodes.first().map(|node| node.bounds)
+    }
+
+    //splits order[start..end] at the median centroid along its widest axis, appending nodes depth-first
+    fn build(&mut self, boxes: &[Aabb], start: usize, end: usize) {
+        let bounds = self.order[start..end]
+            .iter()
+            .map(|&i| boxes[i])
+            .reduce(Aabb::surrounding)
+            .unwrap();
+        let index = self.nodes.len();
+        self.nodes.push(Node {
+            bounds,
+            start,
+            count: end - start,
+        });
+        if end - start <= MAX_LEAF_SIZE {
+            return;
+        }
+
+        let centroids =
+            Aabb::from_points(self.order[start..end].iter().map(|&i| boxes[i].centroid()))
+                .unwrap()
+                .size();
+        let axis = (0..3)
+            .max_by(|&a, &b| centroids.e[a].total_cmp(&centroids.e[b]))
+            .unwrap();
+        let mid = (start + end) / 2;
+        self.order[start..end].select_nth_unstable_by(mid - start, |&a, &b| {
+            boxes[a].centroid().e[axis].total_cmp(&boxes[b].centroid().e[axis])
+        });
+
+        self.build(boxes, start, mid);
+        let second = self.nodes.len();
+        self.build(boxes, mid, end);
+        self.nodes[index].start = second;
+        self.nodes[index].count = 0;
+    }
+
+    //offers every primitive whose box the ray passes through to `hit`, along with the distance of the closest
+    //hit so far; `hit` returns the distance and record of any closer hit, and the closest record wins
+    pub fn traverse<T, F>(&self, r: Ray, t_min: f64, t_max: f64, mut hit: F) -> Option<T>
+    where
+        F: FnMut(usize, f64) -> Option<(f64, T)>,
+    {
+        let mut closest = None;
+        let mut closest_t = t_max;
+        let mut stack = Vec::with_capacity(64);
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+
+        while let Some(index) = stack.pop() {
+            let node = self.nodes[index];
+            if node.bounds.intersect(r, t_min, closest_t).is_none() {
+                continue;
+            }
+
+            if node.count == 0 {
+                stack.push(node.start);
+                stack.push(index + 1);
+                continue;
+            }
+            for &primitive in &self.order[node.start..node.start + node.count] {
+                if let Some((t, rec)) = hit(primitive, closest_t) {
+                    closest_t = t;
+                    closest = Some(rec);
+                }
+            }
+        }
+
+        closest
+    }
+}
@@ -0,0 +1,121 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    ray::{HitRecord, Hittable, Ray},
+    Point,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operation {
+    Union,        //inside either
+    Intersection, //inside both
+    Difference,   //inside the left but not the right
+}
+impl Operation {
+    fn contains(self, in_left: bool, in_right: bool) -> bool {
+        match self {
+            Self::Union => in_left || in_right,
+            Self::Intersection => in_left && in_right,
+            Self::Difference => in_left && !in_right,
+        }
+    }
+}
+
+//constructive solid geometry: a boolean combination of two closed shapes, which may themselves be combinations
+#[derive(Clone)]
+pub struct Csg {
+    operation: Operation,
+    left: Arc<dyn Hittable>,
+    right: Arc<dyn Hittable>,
+}
+impl Csg {
+    pub fn new<L, R>(operation: Operation, left: L, right: R) -> Self
+    where
+        L: Hittable + 'static,
+        R: Hittable + 'static,
+    {
+        Self {
+            operation,
+            left: Arc::new(left),
+            right: Arc::new(right),
+        }
+    }
+
+    pub fn union<L: Hittable + 'static, R: Hittable + 'static>(left: L, right: R) -> Self {
+        Self::new(Operation::Union, left, right)
+    }
+
+    pub fn intersection<L: Hittable + 'static, R: Hittable + 'static>(left: L, right: R) -> Self {
+        Self::new(Operation::Intersection, left, right)
+    }
+
+    pub fn difference<L: Hittable + 'static, R: Hittable + 'static>(left: L, right: R) -> Self {
+        Self::new(Operation::Difference, left, right)
+    }
+}
+impl Hittable for Csg {
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        //whether the ray starts inside each operand depends on crossings past t_max too
+        let left = self.left.crossings(r, t_min, f64::INFINITY);
+        let right = self.right.crossings(r, t_min, f64::INFINITY);
+        let mut in_left = left.first().is_some_and(|rec| !rec.front_face);
+        let mut in_right = right.first().is_some_and(|rec| !rec.front_face);
+        let mut inside = self.operation.contains(in_left, in_right);
+
+        //walk both operands' crossings in order until the combination changes from inside to out or back
+        let (mut left, mut right) = (left.into_iter().peekable(), right.into_iter().peekable());
+        loop {
+            let from_left = match (left.peek(), right.peek()) {
+                (Some(l), Some(r)) => l.t <= r.t,
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (None, None) => return None,
+            };
+            let mut rec = match from_left {
+                true => left.next()?,
+                false => right.next()?,
+            };
+            if rec.t > t_max {
+                return None;
+            }
+
+            if from_left {
+                in_left = !in_left;
+            } else {
+                in_right = !in_right;
+            }
+            let now_inside = self.operation.contains(in_left, in_right);
+            if now_inside != inside {
+                //the record's normal already faces the ray, but the operand's inside may be the result's outside
+                rec.front_face = now_inside;
+                return Some(rec);
+            }
+            inside = now_inside;
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let (left, right) = (self.left.bounding_box(), self.right.bounding_box());
+        match self.operation {
+            Operation::Union => Some(Aabb::surrounding(left?, right?)),
+            Operation::Intersection => match (left, right) {
+                (Some(a), Some(b)) => {
+                    let min = Point::new(
+                        a.min.e[0].max(b.min.e[0]),
+                        a.min.e[1].max(b.min.e[1]),
+                        a.min.e[2].max(b.min.e[2]),
+                    );
+                    let max = Point::new(
+                        a.max.e[0].min(b.max.e[0]).max(min.e[0]),
+                        a.max.e[1].min(b.max.e[1]).max(min.e[1]),
+                        a.max.e[2].min(b.max.e[2]).max(min.e[2]),
+                    );
+                    Some(Aabb::new(min, max))
+                }
+                (a, b) => a.or(b),
+            },
+            Operation::Difference => left,
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//smooths the noise out of low-sample renders after the fact, using the albedo, normal and depth passes to
+//tell noise from edges and texture
+
+use crate::{aov::Aovs, material::Colour, Vec3};
+
+//an edge-avoiding à-trous wavelet filter (Dammertz et al. 2010): repeated 5x5 blurs with taps spreading twice
+//as far apart each time, where neighbours only count as much as their features match. Lighting is filtered
+//with the albedo divided out, so textures stay sharp.
+#[derive(Clone, Copy, Debug)]
+pub struct Denoiser {
+    pub iterations: usize, //each doubles the reach; 5 spans 63 pixels
+    pub colour: f64,       //how different lighting may be and still blend, halved each iteration
+    pub albedo: f64,
+    pub normal: f64,
+    pub depth: f64, //relative to the depth itself, so distant surfaces aren't treated as edges
+}
+impl Default for Denoiser {
+    fn default() -> Self {
+        Self {
+            iterations: 5,
+            colour: 0.5,
+            albedo: 0.1,
+            normal: 0.1,
+            depth: 0.05,
+        }
+    }
+}
+
+impl Denoiser {
+    //the beauty pass with its noise filtered out, still in floating point and before tone mapping
+    pub fn denoise(&self, aovs: &Aovs) -> Vec<Colour> {
+        let (width, height) = (aovs.width, aovs.height);
+
+        //where nothing was hit, or it reflects next to nothing, there's no albedo to divide out
+        let albedo: Vec<Colour> = aovs
+            .albedo
+            .iter()
+            .map(|&a| {
+                Colour::new(
+                    if a.e[0] > 0.01 { a.e[0] } else { 1.0 },
+                    if a.e[1] > 0.01 { a.e[1] } else { 1.0 },
+                    if a.e[2] > 0.01 { a.e[2] } else { 1.0 },
+                )
+            })
+            .collect();
+        let mut lighting: Vec<Colour> = aovs
+            .beauty
+            .iter()
+            .zip(&albedo)
+            .map(|(&c, &a)| c / a)
+            .collect();
+
+        const KERNEL: [f64; 3] = [3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+        for iteration in 0..self.iterations {
+            let step = 1 << iteration;
+            let colour_spread = 2.0 * (self.colour / step as f64).powi(2);
+            let mut filtered = Vec::with_capacity(lighting.len());
+            for y in 0..height {
+                for x in 0..width {
+                    let centre = y * width + x;
+                    let mut sum = Colour::default();
+                    let mut total = 0.0;
+                    for dy in -2..=2isize {
+                        for dx in -2..=2isize {
+                            let (nx, ny) = (x as isize + dx * step, y as isize + dy * step);
+                            if nx < 0 || ny < 0 || nx >= width as isize || ny >= height as isize {
+                                continue;
+                            }
+                            let other = ny as usize * width + nx as usize;
+                            let distance = |a: Vec3, b: Vec3| (a - b).length_squared();
+                            let depth = (aovs.depth[centre] - aovs.depth[other])
+                                / aovs.depth[centre].abs().max(1e-3);
+                            let weight = KERNEL[dx.unsigned_abs()]
+                                * KERNEL[dy.unsigned_abs()]
+                                * (-distance(lighting[centre], lighting[other]) / colour_spread
+                                    - distance(aovs.albedo[centre], aovs.albedo[other])
+                                        / (2.0 * self.albedo * self.albedo)
+                                    - distance(aovs.normal[centre], aovs.normal[other])
+                                        / (2.0 * self.normal * self.normal)
+                                    - depth * depth / (2.0 * self.depth * self.depth))
+                                    .exp();
+                            sum = sum + weight * lighting[other];
+                            total += weight;
+                        }
+                    }
+                    //the centre always counts fully, so the total is never zero
+                    filtered.push(sum / total);
+                }
+            }
+            lighting = filtered;
+        }
+
+        lighting.iter().zip(&albedo).map(|(&l, &a)| l * a).collect()
+    }
+}
+
+//how far one image is from another, such as a denoised render from a converged reference
+pub fn mean_squared_error(image: &[Colour], reference: &[Colour]) -> f64 {
+    assert_eq!(image.len(), reference.len(), "images of the same size");
+    let total: f64 = image
+        .iter()
+        .zip(reference)
+        .map(|(&a, &b)| (a - b).length_squared())
+        .sum();
+    total / (3 * image.len()) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{worlds::simple_scene, Renderer, Viewport};
+
+    #[test]
+    fn denoised_render_is_closer_to_the_reference() {
+        let render = |samples: usize| {
+            let viewport = Viewport::new(48, 27, samples, 10);
+            let (world, camera) = simple_scene(viewport.aspect_ratio());
+            Renderer::new(viewport, camera, world).aovs()
+        };
+        let noisy = render(4);
+        let reference = render(256).beauty;
+        let denoised = Denoiser::default().denoise(&noisy);
+
+        assert!(
+            mean_squared_error(&denoised, &reference)
+                < mean_squared_error(&noisy.beauty, &reference)
+        );
+    }
+}
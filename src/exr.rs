@@ -0,0 +1,87 @@
+//just enough OpenEXR to write uncompressed 32-bit float scanline images, with any number of named channels
+
+use std::io::{self, Write};
+
+//`channels` are full images, top row first; a dot in a name puts the channel in a layer, e.g. "normal.X"
+pub(crate) fn write<W: Write>(
+    mut out: W,
+    width: usize,
+    height: usize,
+    channels: &[(String, Vec<f32>)],
+) -> io::Result<()> {
+    let mut channels: Vec<_> = channels.iter().collect();
+    //readers expect the channels in order of name, both in the header and in each scanline
+    channels.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut header = vec![];
+    //the magic number, then version 2 for single-part scanlines, with long names allowed for deep layer paths
+    header.extend(20000630i32.to_le_bytes());
+    header.extend(0x402u32.to_le_bytes());
+
+    let mut list = vec![];
+    for (name, _) in &channels {
+        list.extend(name.as_bytes());
+        list.push(0);
+        list.extend(2i32.to_le_bytes()); //FLOAT
+        list.extend([0; 4]); //linear, and three reserved bytes
+        list.extend(1i32.to_le_bytes()); //x and y sampling
+        list.extend(1i32.to_le_bytes());
+    }
+    list.push(0);
+    let window: Vec<u8> = [0, 0, width as i32 - 1, height as i32 - 1]
+        .iter()
+        .flat_map(|i| i.to_le_bytes())
+        .collect();
+
+    attribute(&mut header, "channels", "chlist", &list);
+    attribute(&mut header, "compression", "compression", &[0]); //none
+    attribute(&mut header, "dataWindow", "box2i", &window);
+    attribute(&mut header, "displayWindow", "box2i", &window);
+    attribute(&mut header, "lineOrder", "lineOrder", &[0]); //increasing y
+    attribute(
+        &mut header,
+        "pixelAspectRatio",
+        "float",
+        &1f32.to_le_bytes(),
+    );
+    attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+    attribute(
+        &mut header,
+        "screenWindowWidth",
+        "float",
+        &1f32.to_le_bytes(),
+    );
+    header.push(0);
+
+    //a table of where each scanline starts, then the scanlines, each a channel at a time
+    let line_size = channels.len() * width * 4;
+    let first_line = header.len() + height * 8;
+    out.write_all(&header)?;
+    for y in 0..height {
+        out.write_all(&((first_line + y * (line_size + 8)) as u64).to_le_bytes())?;
+    }
+    let mut line = Vec::with_capacity(line_size + 8);
+    for y in 0..height {
+        line.clear();
+        line.extend((y as i32).to_le_bytes());
+        line.extend((line_size as i32).to_le_bytes());
+        for (_, values) in &channels {
+            line.extend(
+                values[y * width..(y + 1) * width]
+                    .iter()
+                    .flat_map(|v| v.to_le_bytes()),
+            );
+        }
+        out.write_all(&line)?;
+    }
+    out.flush()
+}
+
+fn attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    header.extend(name.as_bytes());
+    header.push(0);
+    header.extend(kind.as_bytes());
+    header.push(0);
+    header.extend((value.len() as i32).to_le_bytes());
+    header.extend(value);
+}
@@ -0,0 +1,430 @@
+//glTF 2.0 scenes, as .gltf with separate or embedded buffers, or as binary .glb
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use gltf::{buffer, image, material::AlphaMode, mesh::Mode, Gltf};
+
+use crate::{
+    aabb::Aabb,
+    material::{Colour, Material},
+    mesh::TriangleMesh,
+    principled::Principled,
+    ray::{Camera, Hittable, HittableList, Projection},
+    texture::{srgb_to_linear, Cutout, Detail, Image, Texture},
+    transform::Transform,
+    Point, Vec3,
+};
+
+//why a glTF scene couldn't be imported
+#[derive(Debug)]
+pub enum GltfError {
+    Gltf(gltf::Error),   //the file itself is malformed
+    Io(io::Error),       //a buffer or image it refers to couldn't be read
+    Unsupported(String), //something valid that this renderer can't use, such as JPEG textures
+}
+impl fmt::Display for GltfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Gltf(error) => write!(f, "{}", error),
+            Self::Io(error) => write!(f, "{}", error),
+            Self::Unsupported(what) => write!(f, "unsupported: {}", what),
+        }
+    }
+}
+impl Error for GltfError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Gltf(error) => Some(error),
+            Self::Io(error) => Some(error),
+            Self::Unsupported(_) => None,
+        }
+    }
+}
+impl From<gltf::Error> for GltfError {
+    fn from(error: gltf::Error) -> Self {
+        Self::Gltf(error)
+    }
+}
+impl From<io::Error> for GltfError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+//the default scene's meshes, transformed into place, and the view from its first camera; without a camera,
+//the view is from in front of everything
+pub fn load_gltf<P: AsRef<Path>>(
+    path: P,
+    aspect_ratio: f64,
+) -> Result<(HittableList, Camera), GltfError> {
+    let gltf = Gltf::open(&path)?;
+    let base = path
+        .as_ref()
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    let mut importer = Importer {
+        base,
+        buffers: vec![],
+        images: HashMap::new(),
+    };
+    for buffer in gltf.buffers() {
+        let data = match buffer.source() {
+            buffer::Source::Bin => gltf.blob.clone().ok_or_else(|| {
+                GltfError::Unsupported("a binary buffer outside a .glb".to_string())
+            })?,
+            buffer::Source::Uri(uri) => importer.read_uri(uri)?,
+        };
+        importer.buffers.push(data);
+    }
+
+    let mut world = HittableList::default();
+    let mut bounds: Option<Aabb> = None;
+    let mut camera = None;
+    let scene = match gltf.default_scene().or_else(|| gltf.scenes().next()) {
+        Some(scene) => scene,
+        None => return Err(GltfError::Unsupported("a file without scenes".to_string())),
+    };
+
+    //depth first, carrying each node's transformation down to its children
+    let mut stack: Vec<_> = scene
+        .nodes()
+        .map(|node| (node, Transform::identity()))
+        .collect();
+    while let Some((node, parent)) = stack.pop() {
+        let transform = parent
+            * Transform::from_columns(
+                node.transform()
+                    .matrix()
+                    .map(|column| column.map(f64::from)),
+            );
+        if let Some(mesh) = node.mesh() {
+            for primitive in mesh.primitives() {
+                if let Some(mesh) = importer.primitive(&primitive, transform)? {
+                    if let Some(b) = mesh.bounding_box() {
+                        bounds = Some(bounds.map_or(b, |bounds| Aabb::surrounding(bounds, b)));
+                    }
+                    world.add(mesh);
+                }
+            }
+        }
+        if let (None, Some(gltf_camera)) = (&camera, node.camera()) {
+            camera = Some(view(&gltf_camera, transform, aspect_ratio));
+        }
+        stack.extend(node.children().map(|child| (child, transform)));
+    }
+
+    let camera = match camera {
+        Some(camera) => camera,
+        None => framing(bounds, aspect_ratio),
+    };
+    Ok((world, camera))
+}
+
+struct Importer {
+    base: PathBuf,
+    buffers: Vec<Vec<u8>>,
+    images: HashMap<(usize, bool), Arc<Image>>, //by index and whether it's colour rather than data
+}
+impl Importer {
+    //embedded base64 data, or a file relative to the scene
+    fn read_uri(&self, uri: &str) -> Result<Vec<u8>, GltfError> {
+        if let Some(data) = uri.strip_prefix("data:") {
+            let encoded = data
+                .split_once(";base64,")
+                .map(|(_, encoded)| encoded)
+                .ok_or_else(|| {
+                    GltfError::Unsupported("a data URI that isn't base64".to_string())
+                })?;
+            return base64(encoded).ok_or_else(|| {
+                GltfError::Io(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "bad base64 data",
+                ))
+            });
+        }
+        if uri.contains("://") {
+            return Err(GltfError::Unsupported(format!("remote data at {}", uri)));
+        }
+        Ok(fs::read(self.base.join(percent_decode(uri)))?)
+    }
+
+    fn image(&mut self, image: image::Image, colour: bool) -> Result<Arc<Image>, GltfError> {
+        if let Some(image) = self.images.get(&(image.index(), colour)) {
+            return Ok(image.clone());
+        }
+        let (bytes, mime_type) = match image.source() {
+            image::Source::View { view, mime_type } => {
+                let bytes = self
+                    .buffers
+                    .get(view.buffer().index())
+                    .and_then(|buffer| buffer.get(view.offset()..view.offset() + view.length()))
+                    .ok_or_else(|| {
+                        GltfError::Io(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "an image's buffer view runs past the end of its buffer",
+                        ))
+                    })?;
+                (bytes.to_vec(), Some(mime_type))
+            }
+            image::Source::Uri { uri, mime_type } => (self.read_uri(uri)?, mime_type),
+        };
+        if !bytes.starts_with(b"\x89PNG") {
+            return Err(GltfError::Unsupported(format!(
+                "{} textures, only PNG",
+                mime_type.unwrap_or("non-PNG")
+            )));
+        }
+        let decoded = match colour {
+            true => Image::decode_png(&bytes[..], srgb_to_linear)?,
+            false => Image::decode_png(&bytes[..], |c| c)?,
+        };
+        let decoded = Arc::new(decoded);
+        self.images.insert((image.index(), colour), decoded.clone());
+        Ok(decoded)
+    }
+
+    //a triangle list, with its transformation baked in
+    fn primitive(
+        &mut self,
+        primitive: &gltf::Primitive,
+        transform: Transform,
+    ) -> Result<Option<TriangleMesh>, GltfError> {
+        if primitive.mode() != Mode::Triangles {
+            return Ok(None); //points and lines have no area to hit
+        }
+        let buffers = &self.buffers;
+        let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(Vec::as_slice));
+        let positions: Vec<Point> = match reader.read_positions() {
+            Some(positions) => positions
+                .map(|[x, y, z]| transform.point(Point::new(x as f64, y as f64, z as f64)))
+                .collect(),
+            None => return Ok(None),
+        };
+        let mut indices: Vec<[usize; 3]> = match reader.read_indices() {
+            Some(indices) => {
+                let flat: Vec<usize> = indices.into_u32().map(|i| i as usize).collect();
+                flat.chunks_exact(3).map(|t| [t[0], t[1], t[2]]).collect()
+            }
+            None => (0..positions.len() / 3)
+                .map(|t| [3 * t, 3 * t + 1, 3 * t + 2])
+                .collect(),
+        };
+        if let Some(&index) = indices.iter().flatten().find(|&&i| i >= positions.len()) {
+            return Err(GltfError::Io(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "vertex {} used, but there are only {}",
+                    index,
+                    positions.len()
+                ),
+            )));
+        }
+        //mirroring turns triangles inside out, so wind them the other way to keep their fronts
+        if transform.determinant() < 0.0 {
+            for triangle in &mut indices {
+                triangle.swap(1, 2);
+            }
+        }
+        let normals = reader.read_normals().map(|normals| {
+            normals
+                .map(|[x, y, z]| {
+                    transform
+                        .normal(Vec3::new(x as f64, y as f64, z as f64))
+                        .unit()
+                })
+                .collect()
+        });
+        //glTF puts the origin of texture coordinates at the top left of images, and we put it at the bottom left
+        let uvs = reader.read_tex_coords(0).map(|uvs| {
+            uvs.into_f32()
+                .map(|[u, v]| (u as f64, 1.0 - v as f64))
+                .collect()
+        });
+        let colours: Option<Vec<Colour>> = reader.read_colors(0).map(|colours| {
+            colours
+                .into_rgb_f32()
+                .map(|[r, g, b]| Colour::new(r as f64, g as f64, b as f64))
+                .collect()
+        });
+
+        let material = primitive.material();
+        let pbr = material.pbr_metallic_roughness();
+        let [r, g, b, alpha] = pbr.base_color_factor().map(f64::from);
+        let factor = Colour::new(r, g, b);
+        let base_texture = match pbr.base_color_texture() {
+            Some(info) => Some(self.image(info.texture().source(), true)?),
+            None => None,
+        };
+        let base_colour = match (&base_texture, &colours) {
+            //the vertex colours below already carry the factor
+            (Some(texture), Some(_)) => Texture::VertexTinted(texture.clone()),
+            //the factor scales the texture, so bake it in
+            (Some(texture), None) => Texture::Image(Arc::new(scaled(texture, factor, alpha))),
+            (None, Some(_)) => Texture::VertexColour(factor),
+            (None, None) => Texture::Solid(factor),
+        };
+        let metallic_roughness = match pbr.metallic_roughness_texture() {
+            Some(info) => Some(Texture::Image(self.image(info.texture().source(), false)?)),
+            None => None,
+        };
+        let principled = Principled {
+            base_colour,
+            metallic: pbr.metallic_factor() as f64,
+            roughness: pbr.roughness_factor() as f64,
+            transmission: material
+                .transmission()
+                .map_or(0.0, |t| t.transmission_factor() as f64),
+            ior: material.ior().unwrap_or(1.5) as f64,
+            metallic_roughness,
+            ..Default::default()
+        };
+        let mut surface = Material::Principled(principled);
+        if let Some(normal) = material.normal_texture() {
+            let image = self.image(normal.texture().source(), false)?;
+            surface = Material::Detailed(Box::new(surface), Detail::NormalMap(image));
+        }
+        if let Some(index) = material.index() {
+            surface = surface.with_id(index);
+        }
+
+        let mut mesh = TriangleMesh::new(positions, indices, surface);
+        if let Some(normals) = normals {
+            mesh = mesh.with_normals(normals);
+        }
+        if let Some(uvs) = uvs {
+            mesh = mesh.with_uvs(uvs);
+        }
+        if let Some(colours) = colours {
+            mesh = mesh.with_colours(colours.into_iter().map(|c| c * factor).collect());
+        }
+        //alpha comes from the base colour's texture and factor
+        let mask = || {
+            let white = Arc::new(Image::new(1, 1, vec![Colour::new(1.0, 1.0, 1.0)]));
+            Arc::new(scaled(
+                base_texture.as_ref().unwrap_or(&white),
+                factor,
+                alpha,
+            ))
+        };
+        match material.alpha_mode() {
+            AlphaMode::Opaque => {}
+            AlphaMode::Mask => {
+                let cutoff = material.alpha_cutoff().unwrap_or(0.5) as f64;
+                mesh = mesh.with_cutout(Cutout::Threshold(mask(), cutoff));
+            }
+            AlphaMode::Blend => mesh = mesh.with_cutout(Cutout::Stochastic(mask())),
+        }
+        Ok(Some(mesh))
+    }
+}
+
+//a copy of the texture with its colours and alpha multiplied by factors
+fn scaled(texture: &Image, factor: Colour, alpha: f64) -> Image {
+    let (width, height) = (texture.width(), texture.height());
+    let pixels = (0..width * height)
+        .map(|i| factor * texture.pixel(i % width, i / width))
+        .collect();
+    let opacity = (0..width * height)
+        .map(|i| alpha * texture.alpha(i % width, i / width))
+        .collect();
+    Image::new(width, height, pixels).with_alpha(opacity)
+}
+
+//cameras look down their -z axis with +y up
+fn view(camera: &gltf::Camera, transform: Transform, aspect_ratio: f64) -> Camera {
+    let origin = transform.point(Point::default());
+    let forward = transform.vector(Vec3::new(0.0, 0.0, -1.0));
+    let up = transform.vector(Vec3::new(0.0, 1.0, 0.0));
+    match camera.projection() {
+        gltf::camera::Projection::Perspective(perspective) => Camera::new(
+            origin,
+            origin + forward,
+            up,
+            (perspective.yfov() as f64).to_degrees(),
+            aspect_ratio,
+            0.0,
+            1.0,
+        ),
+        //ymag is half the height of the view
+        gltf::camera::Projection::Orthographic(orthographic) => {
+            Camera::new(origin, origin + forward, up, 90.0, aspect_ratio, 0.0, 1.0)
+                .with_projection(Projection::Orthographic(2.0 * orthographic.ymag() as f64))
+        }
+    }
+}
+
+//looking along -z at the middle of the bounds, from far enough back to see all of them
+fn framing(bounds: Option<Aabb>, aspect_ratio: f64) -> Camera {
+    let bounds = bounds
+        .unwrap_or_else(|| Aabb::new(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0)));
+    let centre = bounds.centroid();
+    let radius = 0.5 * bounds.size().length();
+    let vfov: f64 = 40.0;
+    let distance = radius / (0.5 * vfov.to_radians()).sin();
+    let origin = centre + Vec3::new(0.0, 0.0, distance);
+    Camera::new(
+        origin,
+        centre,
+        Vec3::new(0.0, 1.0, 0.0),
+        vfov,
+        aspect_ratio,
+        0.0,
+        distance,
+    )
+}
+
+fn base64(encoded: &str) -> Option<Vec<u8>> {
+    let value = |c: u8| match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' | b'-' => Some(62),
+        b'/' | b'_' => Some(63),
+        _ => None,
+    };
+    let digits = encoded
+        .bytes()
+        .filter(|&c| c != b'=' && !c.is_ascii_whitespace())
+        .map(value)
+        .collect::<Option<Vec<u8>>>()?;
+    //every four digits hold three bytes
+    let mut decoded = Vec::with_capacity(digits.len() * 3 / 4);
+    for chunk in digits.chunks(4) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, &digit)| {
+            bits | (digit as u32) << (18 - 6 * i)
+        });
+        decoded.extend(&bits.to_be_bytes()[1..chunk.len()]);
+    }
+    Some(decoded)
+}
+
+//%20 and friends, as file names appear in URIs
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
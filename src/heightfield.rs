@@ -0,0 +1,268 @@
+use std::{io, path::Path};
+
+use crate::{
+    aabb::Aabb,
+    material::{Colour, Material},
+    mesh::intersect_triangle,
+    ray::{HitRecord, Hittable, Ray},
+    texture::Image,
+    Point, Vec3,
+};
+
+//terrain from a grid of heights spanning an axis-aligned box, stored with x varying fastest then z;
+//heights run from 0 at the bottom of the box to 1 at the top, and each grid cell is split into two triangles
+#[derive(Clone)]
+pub struct Heightfield {
+    bounds: Aabb,
+    resolution: [usize; 2],
+    heights: Vec<f64>,
+    normals: Vec<Vec3>,
+    cells: Vec<(f64, f64)>, //the lowest and highest point of each cell, so rays can skip those they pass over
+    material: Material,
+}
+impl Heightfield {
+    pub fn new(
+        bounds: Aabb,
+        resolution: [usize; 2],
+        heights: Vec<f64>,
+        material: Material,
+    ) -> Self {
+        let [nx, nz] = resolution;
+        assert!(
+            nx >= 2 && nz >= 2,
+            "a heightfield needs at least 2x2 samples"
+        );
+        assert_eq!(
+            nx * nz,
+            heights.len(),
+            "heightfield resolution doesn't match its heights"
+        );
+        let size = bounds.size();
+        let heights: Vec<f64> = heights
+            .iter()
+            .map(|h| bounds.min.e[1] + h.clamp(0.0, 1.0) * size.e[1])
+            .collect();
+
+        //smooth normals from central differences, one-sided along the edges
+        let (dx, dz) = (size.e[0] / (nx - 1) as f64, size.e[2] / (nz - 1) as f64);
+        let height = |x: usize, z: usize| heights[z * nx + x];
+        let mut normals = Vec::with_capacity(nx * nz);
+        for z in 0..nz {
+            for x in 0..nx {
+                let (x0, x1) = (x.saturating_sub(1), (x + 1).min(nx - 1));
+                let (z0, z1) = (z.saturating_sub(1), (z + 1).min(nz - 1));
+                let slope_x = (height(x1, z) - height(x0, z)) / ((x1 - x0) as f64 * dx);
+                let slope_z = (height(x, z1) - height(x, z0)) / ((z1 - z0) as f64 * dz);
+                normals.push(Vec3::new(-slope_x, 1.0, -slope_z).unit());
+            }
+        }
+
+        let mut cells = Vec::with_capacity((nx - 1) * (nz - 1));
+        for z in 0..nz - 1 {
+            for x in 0..nx - 1 {
+                let corners = [
+                    height(x, z),
+                    height(x + 1, z),
+                    height(x, z + 1),
+                    height(x + 1, z + 1),
+                ];
+                cells.push((
+                    corners.iter().copied().fold(f64::INFINITY, f64::min),
+                    corners.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+                ));
+            }
+        }
+
+        Self {
+            bounds,
+            resolution,
+            heights,
+            normals,
+            cells,
+            material,
+        }
+    }
+
+    //samples a procedural height (noise, erosion, ...) in [0, 1] at every grid point, given its x and z
+    pub fn from_fn<F: Fn(f64, f64) -> f64>(
+        bounds: Aabb,
+        resolution: [usize; 2],
+        height: F,
+        material: Material,
+    ) -> Self {
+        let [nx, nz] = resolution;
+        let size = bounds.size();
+        let mut heights = Vec::with_capacity(nx * nz);
+        for z in 0..nz {
+            for x in 0..nx {
+                heights.push(height(
+                    bounds.min.e[0] + x as f64 / (nx - 1).max(1) as f64 * size.e[0],
+                    bounds.min.e[2] + z as f64 / (nz - 1).max(1) as f64 * size.e[2],
+                ));
+            }
+        }
+        Self::new(bounds, resolution, heights, material)
+    }
+
+    //a greyscale PNG height map, 8 or 16 bits deep, its bottom left at the minimum x and z of the box
+    pub fn open<P: AsRef<Path>>(path: P, bounds: Aabb, material: Material) -> io::Result<Self> {
+        let image = Image::open_linear(path)?;
+        let (width, height) = (image.width(), image.height());
+        if width < 2 || height < 2 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "a height map needs at least 2x2 pixels",
+            ));
+        }
+        //images are stored top row first
+        let heights = (0..width * height)
+            .map(|i| image.pixel(i % width, height - 1 - i / width).e[0])
+            .collect();
+        Ok(Self::new(bounds, [width, height], heights, material))
+    }
+
+    fn vertex(&self, x: usize, z: usize) -> Point {
+        let [nx, nz] = self.resolution;
+        let size = self.bounds.size();
+        Point::new(
+            self.bounds.min.e[0] + x as f64 / (nx - 1) as f64 * size.e[0],
+            self.heights[z * nx + x],
+            self.bounds.min.e[2] + z as f64 / (nz - 1) as f64 * size.e[2],
+        )
+    }
+
+    //the two triangles of a cell, as the grid coordinates of their corners, wound to face up
+    fn triangles(x: usize, z: usize) -> [[(usize, usize); 3]; 2] {
+        [
+            [(x, z), (x, z + 1), (x + 1, z + 1)],
+            [(x, z), (x + 1, z + 1), (x + 1, z)],
+        ]
+    }
+
+    fn hit_cell(
+        &self,
+        x: usize,
+        z: usize,
+        r: Ray,
+        t_min: f64,
+        t_max: f64,
+    ) -> Option<HitRecord<'_>> {
+        let mut closest = None;
+        let mut closest_t = t_max;
+        for corners in Self::triangles(x, z) {
+            let positions = corners.map(|(x, z)| self.vertex(x, z));
+            if let Some((t, b1, b2)) = intersect_triangle(positions, r, t_min, closest_t) {
+                closest_t = t;
+                closest = Some((corners, positions, t, b1, b2));
+            }
+        }
+
+        let (corners, [p0, p1, p2], t, b1, b2) = closest?;
+        let nx = self.resolution[0];
+        let normal = |(x, z): (usize, usize)| self.normals[z * nx + x];
+        let geometric = Vec3::cross(p1 - p0, p2 - p0).unit();
+        let shading = ((1.0 - b1 - b2) * normal(corners[0])
+            + b1 * normal(corners[1])
+            + b2 * normal(corners[2]))
+        .unit();
+
+        let p = r.at(t);
+        let size = self.bounds.size();
+        let u = (p.e[0] - self.bounds.min.e[0]) / size.e[0];
+        let v = (p.e[2] - self.bounds.min.e[2]) / size.e[2];
+        //u runs along x and v along z
+        let tangent = Vec3::new(1.0, 0.0, 0.0);
+        let tangent = (tangent - Vec3::dot(tangent, shading) * shading).unit();
+        let bitangent = Vec3::cross(tangent, shading);
+
+        let front_face = HitRecord::front_face(r, geometric);
+        let facing = if front_face { 1.0 } else { -1.0 };
+        Some(
+            HitRecord {
+                t,
+                p,
+                normal: facing * shading,
+                geometric_normal: facing * geometric,
+                material: &self.material,
+                front_face,
+                emitted: Colour::default(),
+                u,
+                v,
+                tangent,
+                bitangent,
+                vertex_colour: None,
+                undetailed_normal: None,
+                object_id: None,
+            }
+            .with_detail(),
+        )
+    }
+}
+impl Hittable for Heightfield {
+    //walks the cells under the ray with a 2D DDA, testing triangles only in cells whose height range the ray is within
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let (t_enter, t_exit) = self.bounds.intersect(r, t_min, t_max)?;
+        let [nx, nz] = self.resolution;
+        let size = self.bounds.size();
+        let cell_size = [size.e[0] / (nx - 1) as f64, size.e[2] / (nz - 1) as f64];
+        let cells = [nx - 1, nz - 1];
+
+        //per horizontal axis (x, then z): the current cell, which way to step, when the next step comes, and how often
+        let entry = r.at(t_enter);
+        let mut cell = [0; 2];
+        let mut step = [0; 2];
+        let mut t_next = [f64::INFINITY; 2];
+        let mut t_delta = [f64::INFINITY; 2];
+        for (i, &axis) in [0, 2].iter().enumerate() {
+            let local = (entry.e[axis] - self.bounds.min.e[axis]) / cell_size[i];
+            cell[i] = (local.floor().max(0.0) as usize).min(cells[i] - 1);
+            let direction = r.direction.e[axis];
+            if direction > 0.0 {
+                step[i] = 1;
+                t_delta[i] = cell_size[i] / direction;
+                t_next[i] = t_enter + (cell[i] as f64 + 1.0 - local) * t_delta[i];
+            } else if direction < 0.0 {
+                step[i] = -1;
+                t_delta[i] = -cell_size[i] / direction;
+                t_next[i] = t_enter + (local - cell[i] as f64) * t_delta[i];
+            }
+        }
+
+        let mut t = t_enter;
+        loop {
+            let t_leave = t_next[0].min(t_next[1]).min(t_exit);
+            let (low, high) = self.cells[cell[1] * cells[0] + cell[0]];
+            let (y0, y1) = (r.at(t).e[1], r.at(t_leave).e[1]);
+            if y0.min(y1) <= high && y0.max(y1) >= low {
+                //pad the cell's interval a little so hits exactly on its edges aren't lost
+                let padding = 1e-9 * t_leave.abs().max(1.0);
+                let hit = self.hit_cell(
+                    cell[0],
+                    cell[1],
+                    r,
+                    (t - padding).max(t_min),
+                    (t_leave + padding).min(t_max),
+                );
+                if hit.is_some() {
+                    return hit;
+                }
+            }
+            if t_leave >= t_exit {
+                return None;
+            }
+
+            let i = if t_next[0] < t_next[1] { 0 } else { 1 };
+            let next = cell[i] as isize + step[i];
+            if next < 0 || next >= cells[i] as isize {
+                return None;
+            }
+            cell[i] = next as usize;
+            t = t_next[i];
+            t_next[i] += t_delta[i];
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.bounds)
+    }
+}
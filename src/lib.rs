@@ -1,13 +1,44 @@
+pub mod aabb;
+pub mod animation;
+pub mod aov;
+mod bvh;
+pub mod csg;
+pub mod denoise;
+mod exr;
+pub mod gltf_import;
+pub mod heightfield;
+pub mod lpe;
 pub mod material;
+pub mod medium;
+pub mod mesh;
+mod microfacet;
+pub mod noise;
+pub mod pbrt;
+mod ply;
+pub mod principled;
 pub mod ray;
+pub mod scene;
+pub mod sdf;
+pub mod shapes;
+pub mod sky;
+mod stl;
+pub mod texture;
+pub mod transform;
 pub mod worlds;
 mod vec3;
+pub mod y4m;
 
 use material::{Colour, RGBColour};
-use ray::{Camera, HittableList};
+use ray::{Camera, Hittable, HittableList, Ray};
 
 pub use vec3::{Point, Vec3};
 
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
 use rand::Rng;
 
 #[derive(Clone)]
@@ -18,7 +49,8 @@ pub struct Renderer {
 }
 
 impl Renderer {
-    pub fn new(viewport: Viewport, camera: Camera, world: HittableList) -> Self {
+    pub fn new(viewport: Viewport, camera: Camera, mut world: HittableList) -> Self {
+        world.build_bvh();
         Self {
             viewport,
             camera,
@@ -27,18 +59,107 @@ impl Renderer {
     }
 
     pub fn pixel(&self, x: usize, y: usize) -> RGBColour {
-        let mut rng = rand::thread_rng();
+        RGBColour::from(self.radiance(x, y, |_| ()))
+    }
 
+    //a pixel's exposed colour before tone mapping, showing each of its camera rays to `camera_ray` first
+    fn radiance(&self, x: usize, y: usize, mut camera_ray: impl FnMut(Ray)) -> Colour {
         let mut pixel_colour = Colour::default();
-        for _ in 0..self.viewport.samples {
+        for ray in self.camera_rays(x, y) {
+            camera_ray(ray);
+            pixel_colour = pixel_colour + ray.colour(&self.world, self.viewport.depth);
+        }
+        self.develop(x, y, pixel_colour)
+    }
+
+    //the rays for each of a pixel's samples, jittered across it
+    fn camera_rays(&self, x: usize, y: usize) -> impl Iterator<Item = Ray> + '_ {
+        let mut rng = rand::thread_rng();
+        (0..self.viewport.samples).filter_map(move |_| {
             let u = (x as f64 + rng.gen::<f64>()) / (self.viewport.width - 1) as f64;
             let v = (y as f64 + rng.gen::<f64>()) / (self.viewport.height - 1) as f64;
-            let ray = self.camera.get_ray(u, v);
+            //parts of the image the projection doesn't reach stay black
+            self.camera.get_ray(u, v)
+        })
+    }
 
-            pixel_colour = pixel_colour + ray.colour(&self.world, self.viewport.depth);
+    //the light summed over a pixel's samples, averaged and exposed as the camera's settings record it
+    fn develop(&self, x: usize, y: usize, total: Colour) -> Colour {
+        let u = (x as f64 + 0.5) / (self.viewport.width - 1) as f64;
+        let v = (y as f64 + 0.5) / (self.viewport.height - 1) as f64;
+        self.camera
+            .develop(total / self.viewport.samples as f64, u, v)
+    }
+
+    //the frame split into a pass for each light path expression, which add up to the beauty
+    pub fn light_paths(&self, paths: &lpe::LightPaths) -> lpe::Passes {
+        let names = paths.names();
+        let mut buffers = vec![Vec::with_capacity(self.viewport.area()); names.len()];
+        for index in 0..self.viewport.area() {
+            let x = index % self.viewport.width;
+            let y = self.viewport.height - 1 - (index / self.viewport.width);
+            let mut totals = vec![Colour::default(); names.len()];
+            for ray in self.camera_rays(x, y) {
+                ray.trace(&self.world, self.viewport.depth, |path, light| {
+                    let pass = paths.pass(path);
+                    totals[pass] = totals[pass] + light;
+                });
+            }
+            for (buffer, total) in buffers.iter_mut().zip(totals) {
+                buffer.push(self.develop(x, y, total));
+            }
+        }
+        lpe::Passes {
+            width: self.viewport.width,
+            height: self.viewport.height,
+            passes: names.into_iter().zip(buffers).collect(),
         }
+    }
 
-        RGBColour::from(pixel_colour / self.viewport.samples as f64)
+    //the beauty pass in floating point, with albedo, normal, depth, position, UV and ID passes from the
+    //first hits of the same camera rays
+    pub fn aovs(&self) -> aov::Aovs {
+        let mut aovs = aov::Aovs::new(self.viewport.width, self.viewport.height);
+        for index in 0..self.viewport.area() {
+            let mut hits = Vec::with_capacity(self.viewport.samples);
+            let beauty = self.radiance(
+                index % self.viewport.width,
+                self.viewport.height - 1 - (index / self.viewport.width),
+                |ray| {
+                    let rec = self.world.hit(ray, 0.00001, f64::INFINITY);
+                    hits.push(rec.map(|rec| aov::Sample {
+                        albedo: rec.material.albedo(&rec),
+                        normal: rec.normal,
+                        depth: self.camera.depth(rec.p),
+                        position: rec.p,
+                        uv: (rec.u, rec.v),
+                        object_id: rec.object_id,
+                        material_id: rec.material.id(),
+                    }));
+                },
+            );
+            aovs.push(beauty, &hits, self.viewport.samples);
+        }
+        aovs
+    }
+
+    //a frame rendered with its passes and denoised before tone mapping, for when there's no time for
+    //enough samples
+    pub fn denoised_frame(&self, denoiser: &denoise::Denoiser) -> Vec<u8> {
+        denoiser
+            .denoise(&self.aovs())
+            .into_iter()
+            .flat_map(|colour| <[u8; 3]>::from(RGBColour::from(colour)))
+            .collect()
+    }
+
+    //the scene graph node seen through the middle of a pixel, counting y up from the bottom like `pixel`
+    pub fn pick(&self, x: usize, y: usize) -> Option<scene::NodeId> {
+        let u = (x as f64 + 0.5) / (self.viewport.width - 1) as f64;
+        let v = (y as f64 + 0.5) / (self.viewport.height - 1) as f64;
+        self.world
+            .hit(self.camera.get_ray(u, v)?, 0.00001, f64::INFINITY)?
+            .object_id
     }
 
     pub fn line(&self, n: usize) -> Vec<u8> {
@@ -69,6 +190,94 @@ impl Renderer {
 
         component_vec
     }
+
+    //the camera it renders through, as a template for `CameraPath::with_camera`
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    //the same world through another camera, without rebuilding it
+    pub fn with_camera(&self, camera: Camera) -> Self {
+        Self {
+            camera,
+            ..self.clone()
+        }
+    }
+
+    //a numbered png for each camera, e.g. from `CameraPath::frames` on a path that takes its other settings
+    //from this renderer's camera with `CameraPath::with_camera`, written to `directory` as
+    //`<prefix>0000.png`, `<prefix>0001.png` and so on; the paths written, in order
+    pub fn sequence<P: AsRef<Path>>(
+        &self,
+        cameras: impl IntoIterator<Item = Camera>,
+        directory: P,
+        prefix: &str,
+    ) -> io::Result<Vec<PathBuf>> {
+        let mut paths = vec![];
+        for (index, camera) in cameras.into_iter().enumerate() {
+            let path = directory
+                .as_ref()
+                .join(format!("{}{:04}.png", prefix, index));
+            let mut encoder = png::Encoder::new(
+                BufWriter::new(File::create(&path)?),
+                self.viewport.width as u32,
+                self.viewport.height as u32,
+            );
+            encoder.set_color(png::ColorType::RGB);
+            encoder
+                .write_header()?
+                .write_image_data(&self.with_camera(camera).frame())?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+
+    //a frame for each camera as one y4m video, to a file or straight into an encoder through stdout
+    pub fn stream<W: Write>(
+        &self,
+        cameras: impl IntoIterator<Item = Camera>,
+        out: W,
+        fps: f64,
+    ) -> io::Result<()> {
+        let mut video = y4m::Y4mWriter::new(out, self.viewport.width, self.viewport.height, fps)?;
+        for camera in cameras {
+            video.write_frame(&self.with_camera(camera).frame())?;
+        }
+        video.flush()
+    }
+
+    //left and right eye renderers, `ipd` apart and converging at `convergence`, for rendering or saving separately
+    pub fn stereo(&self, ipd: f64, convergence: f64) -> (Self, Self) {
+        let eye = |offset: f64| Self {
+            camera: self.camera.clone().with_eye(offset, convergence),
+            ..self.clone()
+        };
+        (eye(-0.5 * ipd), eye(0.5 * ipd))
+    }
+
+    //both eyes in one frame, twice as wide or twice as tall as the viewport
+    pub fn stereo_frame(&self, ipd: f64, convergence: f64, layout: StereoLayout) -> Vec<u8> {
+        let (left, right) = self.stereo(ipd, convergence);
+        let (left, right) = (left.frame(), right.frame());
+        match layout {
+            StereoLayout::SideBySide => {
+                let row = self.viewport.width * 3;
+                left.chunks_exact(row)
+                    .zip(right.chunks_exact(row))
+                    .flat_map(|(left, right)| left.iter().chain(right))
+                    .copied()
+                    .collect()
+            }
+            StereoLayout::TopBottom => [left, right].concat(),
+        }
+    }
+}
+
+//where each eye goes in a stereo frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StereoLayout {
+    SideBySide, //left eye on the left
+    TopBottom,  //left eye on top, as most 360 video players expect
 }
 
 #[derive(Clone, Copy)]
@@ -0,0 +1,240 @@
+//light path expressions, which sort the light reaching the camera by the way it got there, so lighters can
+//adjust each kind separately and add them back up
+
+use std::{
+    error::Error,
+    fmt,
+    fs::File,
+    io::{self, BufWriter},
+    path::Path,
+};
+
+use crate::{exr, material::Colour};
+
+//what happened to the light at one bounce along its path
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    Diffuse,      //D
+    Specular,     //S, glossy reflection included
+    Transmission, //T, through a surface
+    Volume,       //V, scattered inside a medium
+}
+impl Event {
+    fn letter(self) -> u8 {
+        match self {
+            Self::Diffuse => b'D',
+            Self::Specular => b'S',
+            Self::Transmission => b'T',
+            Self::Volume => b'V',
+        }
+    }
+}
+
+//why an expression couldn't be read
+#[derive(Debug)]
+pub enum LpeError {
+    Unexpected(usize, char), //a character that can't go where it is; its position
+    Unclosed,                //a [ without its ]
+}
+impl fmt::Display for LpeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Unexpected(position, c) => write!(f, "unexpected '{}' at {}", c, position),
+            Self::Unclosed => write!(f, "a [ is never closed"),
+        }
+    }
+}
+impl Error for LpeError {}
+
+#[derive(Clone, Debug)]
+enum Atom {
+    Letter(u8),
+    Any,                //any event, but not C or L
+    Set(Vec<u8>, bool), //the letters, and whether it's any event but them
+}
+impl Atom {
+    fn matches(&self, letter: u8) -> bool {
+        match self {
+            Self::Letter(l) => *l == letter,
+            Self::Any => b"DSTV".contains(&letter),
+            Self::Set(letters, false) => letters.contains(&letter),
+            Self::Set(letters, true) => Self::Any.matches(letter) && !letters.contains(&letter),
+        }
+    }
+}
+
+//a regular expression over a path written from the camera out: C, a letter for each event, then L for the
+//emitter or sky the light came from. `.` matches any event, `[DS]` any of those listed and `[^T]` any event
+//but those, and `*`, `+` and `?` repeat what's before them; "CD.+L", say, is indirect diffuse
+#[derive(Clone, Debug)]
+pub struct Lpe {
+    atoms: Vec<(Atom, usize, usize)>, //each with the least and most times it may repeat
+}
+impl Lpe {
+    pub fn parse(expression: &str) -> Result<Self, LpeError> {
+        let mut atoms: Vec<(Atom, usize, usize)> = vec![];
+        let mut chars = expression.char_indices().peekable();
+        while let Some((position, c)) = chars.next() {
+            let atom = match c {
+                'C' | 'D' | 'S' | 'T' | 'V' | 'L' => Atom::Letter(c as u8),
+                '.' => Atom::Any,
+                '[' => {
+                    let negated = chars.next_if(|&(_, c)| c == '^').is_some();
+                    let mut letters = vec![];
+                    loop {
+                        match chars.next() {
+                            Some((_, ']')) => break,
+                            Some((_, c @ ('C' | 'D' | 'S' | 'T' | 'V' | 'L'))) => {
+                                letters.push(c as u8)
+                            }
+                            Some((position, c)) => return Err(LpeError::Unexpected(position, c)),
+                            None => return Err(LpeError::Unclosed),
+                        }
+                    }
+                    Atom::Set(letters, negated)
+                }
+                '*' | '+' | '?' => {
+                    let repeated = match atoms.last_mut() {
+                        Some(last) if (last.1, last.2) == (1, 1) => last,
+                        _ => return Err(LpeError::Unexpected(position, c)),
+                    };
+                    (repeated.1, repeated.2) = match c {
+                        '*' => (0, usize::MAX),
+                        '+' => (1, usize::MAX),
+                        _ => (0, 1),
+                    };
+                    continue;
+                }
+                _ => return Err(LpeError::Unexpected(position, c)),
+            };
+            atoms.push((atom, 1, 1));
+        }
+        Ok(Self { atoms })
+    }
+
+    //whether light that took this path, camera first, matches
+    pub fn matches(&self, path: &[Event]) -> bool {
+        let letters: Vec<u8> = std::iter::once(b'C')
+            .chain(path.iter().map(|event| event.letter()))
+            .chain(std::iter::once(b'L'))
+            .collect();
+        Self::match_from(&self.atoms, &letters)
+    }
+
+    //backtracking, trying the longest repeats first
+    fn match_from(atoms: &[(Atom, usize, usize)], letters: &[u8]) -> bool {
+        let (atom, least, most) = match atoms.first() {
+            Some(first) => first,
+            None => return letters.is_empty(),
+        };
+        let available = letters.iter().take_while(|&&l| atom.matches(l)).count();
+        (*least..=available.min(*most))
+            .rev()
+            .any(|count| Self::match_from(&atoms[1..], &letters[count..]))
+    }
+}
+
+//named expressions, a pass for each. Light goes to the first pass whose expression its path matches, and
+//light none of them match to a final "other" pass, so the passes always add up to the beauty
+#[derive(Clone, Default)]
+pub struct LightPaths {
+    passes: Vec<(String, Lpe)>,
+}
+impl LightPaths {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, name: &str, expression: &str) -> Result<Self, LpeError> {
+        self.passes
+            .push((name.to_string(), Lpe::parse(expression)?));
+        Ok(self)
+    }
+
+    //emission, direct and indirect diffuse and specular, transmission and volume, which between them cover
+    //every path
+    pub fn standard() -> Self {
+        [
+            ("emission", "CL"),
+            ("direct_diffuse", "CDL"),
+            ("indirect_diffuse", "CD.+L"),
+            ("direct_specular", "CSL"),
+            ("indirect_specular", "CS.+L"),
+            ("transmission", "CT.*L"),
+            ("volume", "CV.*L"),
+        ]
+        .iter()
+        .fold(Self::new(), |paths, (name, expression)| {
+            paths.with(name, expression).unwrap()
+        })
+    }
+
+    //the pass light along this path belongs in, counting "other" as the one after the last
+    pub(crate) fn pass(&self, path: &[Event]) -> usize {
+        self.passes
+            .iter()
+            .position(|(_, lpe)| lpe.matches(path))
+            .unwrap_or(self.passes.len())
+    }
+
+    //the names of the passes, "other" last
+    pub fn names(&self) -> Vec<String> {
+        self.passes
+            .iter()
+            .map(|(name, _)| name.clone())
+            .chain(std::iter::once("other".to_string()))
+            .collect()
+    }
+}
+
+//a frame split by light path, each pass exposed but not tone mapped, top row first like `Renderer::frame`
+pub struct Passes {
+    pub width: usize,
+    pub height: usize,
+    pub passes: Vec<(String, Vec<Colour>)>,
+}
+impl Passes {
+    pub fn get(&self, name: &str) -> Option<&[Colour]> {
+        self.passes
+            .iter()
+            .find(|(pass, _)| pass == name)
+            .map(|(_, pixels)| &pixels[..])
+    }
+
+    //all the passes added back together
+    pub fn beauty(&self) -> Vec<Colour> {
+        let mut beauty = vec![Colour::default(); self.width * self.height];
+        for (_, pixels) in &self.passes {
+            for (sum, &pixel) in beauty.iter_mut().zip(pixels) {
+                *sum = *sum + pixel;
+            }
+        }
+        beauty
+    }
+
+    //every pass as a layer of one EXR, with their sum as plain R, G and B
+    pub fn write_exr<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let beauty = self.beauty();
+        let layers = std::iter::once(("", &beauty)).chain(
+            self.passes
+                .iter()
+                .map(|(name, pixels)| (name.as_str(), pixels)),
+        );
+        let mut channels = vec![];
+        for (layer, pixels) in layers {
+            for (axis, channel) in ["R", "G", "B"].iter().enumerate() {
+                let name = match layer {
+                    "" => channel.to_string(),
+                    _ => format!("{}.{}", layer, channel),
+                };
+                channels.push((name, pixels.iter().map(|c| c.e[axis] as f32).collect()));
+            }
+        }
+        exr::write(
+            BufWriter::new(File::create(path)?),
+            self.width,
+            self.height,
+            &channels,
+        )
+    }
+}
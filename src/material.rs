@@ -1,7 +1,11 @@
+use crate::lpe::Event;
+use crate::microfacet::{self, Frame};
+use crate::principled::Principled;
 use crate::ray::{HitRecord, Ray};
+use crate::texture::Detail;
 use crate::vec3::Vec3;
 
-use std::fmt;
+use std::{f64::consts::PI, fmt};
 
 use rand::Rng;
 
@@ -36,11 +40,128 @@ impl From<RGBColour> for [u8; 3] {
     }
 }
 
-#[derive(Clone, Copy)]
+//the linear colour of an ideal radiator at this temperature, with a luminance of 1; candlelight is about
+//1900K, tungsten bulbs 2700-3200K and daylight 5500-6500K
+pub fn blackbody(kelvin: f64) -> Colour {
+    xyz_to_rgb(blackbody_xyz(kelvin))
+}
+
+//the same in CIE XYZ, where even the reddest of them stays positive
+fn blackbody_xyz(kelvin: f64) -> Vec3 {
+    //Planck's law, leaving out the constant factor
+    let xyz = spectrum_to_xyz(|lambda| {
+        (lambda * 1e-9).powi(-5) / ((1.4388e7 / (lambda * kelvin)).exp() - 1.0)
+    });
+    xyz / xyz.e[1]
+}
+
+//the rows of a matrix taking linear colours lit by a `kelvin` black body to how they'd look under a 6500K
+//one, scaling each kind of cone's response as the eye adapts (von Kries, in Bradford's cone space). The
+//cones' gains are limited, since below about 2000K there's next to nothing left for the blue ones to amplify
+pub(crate) fn white_balance(kelvin: f64) -> [Vec3; 3] {
+    const MAX_GAIN: f64 = 16.0;
+    let bradford = [
+        Vec3::new(0.8951, 0.2664, -0.1614),
+        Vec3::new(-0.7502, 1.7135, 0.0367),
+        Vec3::new(0.0389, -0.0685, 1.0296),
+    ];
+    let bradford_inverse = [
+        Vec3::new(0.9869929, -0.1470543, 0.1599627),
+        Vec3::new(0.4323053, 0.5183603, 0.0492912),
+        Vec3::new(-0.0085287, 0.0400428, 0.9684867),
+    ];
+    let rgb_to_xyz = [
+        Vec3::new(0.4124, 0.3576, 0.1805),
+        Vec3::new(0.2126, 0.7152, 0.0722),
+        Vec3::new(0.0193, 0.1192, 0.9505),
+    ];
+    let xyz_to_rgb = [
+        Vec3::new(3.2406, -1.5372, -0.4986),
+        Vec3::new(-0.9689, 1.8758, 0.0415),
+        Vec3::new(0.0557, -0.2040, 1.0570),
+    ];
+    let apply = |rows: &[Vec3; 3], v: Vec3| {
+        Vec3::new(
+            Vec3::dot(rows[0], v),
+            Vec3::dot(rows[1], v),
+            Vec3::dot(rows[2], v),
+        )
+    };
+
+    let (target, source) = (
+        apply(&bradford, blackbody_xyz(6500.0)),
+        apply(&bradford, blackbody_xyz(kelvin)),
+    );
+    let gains = Vec3::new(
+        target.e[0] / source.e[0].max(target.e[0] / MAX_GAIN),
+        target.e[1] / source.e[1].max(target.e[1] / MAX_GAIN),
+        target.e[2] / source.e[2].max(target.e[2] / MAX_GAIN),
+    );
+    //each column is where the matrix takes one of the primaries
+    let column = |primary: Vec3| {
+        let cones = apply(&bradford, apply(&rgb_to_xyz, primary));
+        apply(&xyz_to_rgb, apply(&bradford_inverse, gains * cones))
+    };
+    let [r, g, b] = [
+        column(Vec3::new(1.0, 0.0, 0.0)),
+        column(Vec3::new(0.0, 1.0, 0.0)),
+        column(Vec3::new(0.0, 0.0, 1.0)),
+    ];
+    [
+        Vec3::new(r.e[0], g.e[0], b.e[0]),
+        Vec3::new(r.e[1], g.e[1], b.e[1]),
+        Vec3::new(r.e[2], g.e[2], b.e[2]),
+    ]
+}
+
+//the CIE XYZ colour of a spectrum, given in nm from 380 to 780, up to a constant factor
+pub(crate) fn spectrum_to_xyz(radiance: impl Fn(f64) -> f64) -> Vec3 {
+    //Wyman, Sloan and Shirley's fit to the CIE 1931 colour matching functions
+    let lobe = |lambda: f64, mean: f64, below: f64, above: f64| {
+        let spread = if lambda < mean { below } else { above };
+        (-0.5 * ((lambda - mean) / spread).powi(2)).exp()
+    };
+    let mut xyz = Vec3::default();
+    for step in 0..=80 {
+        let lambda = 380.0 + 5.0 * step as f64;
+        xyz = xyz
+            + radiance(lambda)
+                * Vec3::new(
+                    1.056 * lobe(lambda, 599.8, 37.9, 31.0)
+                        + 0.362 * lobe(lambda, 442.0, 16.0, 26.7)
+                        - 0.065 * lobe(lambda, 501.1, 20.4, 26.2),
+                    0.821 * lobe(lambda, 568.8, 46.9, 40.5)
+                        + 0.286 * lobe(lambda, 530.9, 16.3, 31.1),
+                    1.217 * lobe(lambda, 437.0, 11.8, 36.0)
+                        + 0.681 * lobe(lambda, 459.0, 26.0, 13.8),
+                );
+    }
+    xyz
+}
+
+//to linear sRGB; the deepest reds and cyans fall just outside it
+pub(crate) fn xyz_to_rgb(xyz: Vec3) -> Colour {
+    let [x, y, z] = xyz.e;
+    Colour::new(
+        (3.2406 * x - 1.5372 * y - 0.4986 * z).max(0.0),
+        (-0.9689 * x + 1.8758 * y + 0.0415 * z).max(0.0),
+        (0.0557 * x - 0.2040 * y + 1.0570 * z).max(0.0),
+    )
+}
+
+#[derive(Clone)]
 pub enum Material {
-    Lambertian(Colour), //diffuse, non-reflective, opaque
-    Metal(Colour, f64), //reflective, opaque
-    Dielectric(f64),    //semi-reflective, transparent, refractory (glass, e.g.)
+    Lambertian(Colour),                //diffuse, non-reflective, opaque
+    Metal(Colour, f64),                //reflective, opaque; albedo and roughness
+    Conductor(Colour, Colour, f64),    //rough metal from optical constants; eta, k, roughness
+    Dielectric(f64),                   //semi-reflective, transparent, refractory (glass, e.g.)
+    RoughDielectric(f64, f64, Colour), //frosted glass; ir, roughness, tint per unit of depth
+    Isotropic(Colour),                 //participating media, scatters evenly in all directions
+    HenyeyGreenstein(Colour, f64),     //participating media, asymmetry g in (-1, 1)
+    Principled(Principled),            //artist-friendly uber material, textureable
+    Detailed(Box<Material>, Detail),   //any material with a normal or bump map
+    Identified(Box<Material>, usize),  //any material with its own number in the material ID pass
+    DiffuseLight(Colour),              //emits evenly from its front face, scatters nothing
 }
 impl Default for Material {
     fn default() -> Self {
@@ -48,8 +169,64 @@ impl Default for Material {
     }
 }
 impl Material {
-    pub fn scatter(self, r_in: Ray, rec: &HitRecord) -> Option<(Colour, Ray)> {
-        match self {
+    //numbers the material for the material ID pass; materials without one are left out of it
+    pub fn with_id(self, id: usize) -> Self {
+        Self::Identified(Box::new(self), id)
+    }
+
+    pub fn id(&self) -> Option<usize> {
+        match *self {
+            Self::Identified(_, id) => Some(id),
+            Self::Detailed(ref material, _) => material.id(),
+            _ => None,
+        }
+    }
+
+    //the normal or bump map to apply to hits on this material, if it has one
+    pub(crate) fn detail(&self) -> Option<&Detail> {
+        match *self {
+            Self::Detailed(_, ref detail) => Some(detail),
+            Self::Identified(ref material, _) => material.detail(),
+            _ => None,
+        }
+    }
+
+    //optical constants sampled at roughly 650nm, 550nm and 450nm
+    pub fn gold(roughness: f64) -> Self {
+        Self::Conductor(
+            Colour::new(0.143, 0.374, 1.442),
+            Colour::new(3.983, 2.385, 1.603),
+            roughness,
+        )
+    }
+
+    pub fn copper(roughness: f64) -> Self {
+        Self::Conductor(
+            Colour::new(0.200, 0.924, 1.102),
+            Colour::new(3.912, 2.452, 2.142),
+            roughness,
+        )
+    }
+
+    pub fn aluminium(roughness: f64) -> Self {
+        Self::Conductor(
+            Colour::new(1.657, 0.880, 0.521),
+            Colour::new(9.224, 6.270, 4.837),
+            roughness,
+        )
+    }
+
+    pub fn silver(roughness: f64) -> Self {
+        Self::Conductor(
+            Colour::new(0.155, 0.117, 0.138),
+            Colour::new(4.828, 3.122, 2.147),
+            roughness,
+        )
+    }
+
+    //the attenuation and direction of the light's next bounce, and what kind of bounce it is
+    pub fn scatter(&self, r_in: Ray, rec: &HitRecord) -> Option<(Colour, Ray, Event)> {
+        match *self {
             Self::Lambertian(albedo) => {
                 let scatter_direction = rec.normal + Vec3::random_unit();
                 Some((
@@ -63,16 +240,14 @@ impl Material {
                             false => scatter_direction,
                         },
                     ),
+                    Event::Diffuse,
                 ))
             }
-            Self::Metal(albedo, fuzziness) => {
-                let reflected = r_in.direction.reflect(rec.normal).unit();
-                let scattered = Ray::new(rec.p, reflected + fuzziness * Vec3::random_unit());
-                //TODO: why do we need this to return an option, why does this check matter? preventing subsurface scattering?
-                match Vec3::dot(scattered.direction, rec.normal) > 0.0 {
-                    false => None,
-                    true => Some((albedo, scattered)),
-                }
+            Self::Metal(albedo, roughness) => {
+                scatter_microfacet(r_in, rec, roughness, |cosine| schlick_metal(albedo, cosine))
+            }
+            Self::Conductor(eta, k, roughness) => {
+                scatter_microfacet(r_in, rec, roughness, |cosine| conductor(eta, k, cosine))
             }
             Self::Dielectric(ir) => {
                 let refraction_ratio = if rec.front_face { ir.recip() } else { ir };
@@ -83,18 +258,294 @@ impl Material {
 
                 let mut rng = rand::thread_rng();
 
-                let direction = if cannot_refract
+                let (direction, event) = if cannot_refract
                     || schlick_reflectance(cos_theta, refraction_ratio) > rng.gen()
                 {
-                    unit_direction.reflect(rec.normal)
+                    (unit_direction.reflect(rec.normal), Event::Specular)
                 } else {
-                    unit_direction.refract(rec.normal, refraction_ratio)
+                    (
+                        unit_direction.refract(rec.normal, refraction_ratio),
+                        Event::Transmission,
+                    )
                 };
 
-                Some((Colour::new(1.0, 1.0, 1.0), Ray::new(rec.p, direction)))
+                Some((
+                    Colour::new(1.0, 1.0, 1.0),
+                    Ray::new(rec.p, direction),
+                    event,
+                ))
+            }
+            Self::RoughDielectric(ir, roughness, tint) => {
+                let frame = Frame::new(rec.normal);
+                let wo = frame.to_local(-r_in.direction.unit());
+                if wo.e[2] <= 0.0 {
+                    return None;
+                }
+
+                let alpha = microfacet::alpha(roughness);
+                let h = microfacet::sample_visible_normal(wo, alpha);
+                let cos_i = Vec3::dot(wo, h);
+                let refraction_ratio = if rec.front_face { ir.recip() } else { ir };
+
+                //choose reflection or transmission in proportion to the exact fresnel term,
+                //after which microfacet reflection and refraction share the weight G2 / G1
+                let mut rng = rand::thread_rng();
+                let reflect = microfacet::fresnel_dielectric(cos_i, refraction_ratio) > rng.gen();
+                let wi = if reflect {
+                    (-wo).reflect(h)
+                } else {
+                    let cos_t = (1.0 - refraction_ratio.powi(2) * (1.0 - cos_i * cos_i)).sqrt();
+                    (refraction_ratio * cos_i - cos_t) * h - refraction_ratio * wo
+                };
+                //reflections must stay on this side of the surface and refractions cross it
+                if (wi.e[2] > 0.0) != reflect || wi.e[2] == 0.0 {
+                    return None;
+                }
+
+                let weight = microfacet::g2(wo, wi, alpha) / microfacet::g1(wo, alpha);
+                Some((
+                    weight * beer_lambert(r_in, rec, tint),
+                    Ray::new(rec.p, frame.to_world(wi)),
+                    if reflect {
+                        Event::Specular
+                    } else {
+                        Event::Transmission
+                    },
+                ))
+            }
+            Self::Isotropic(albedo) => {
+                Some((albedo, Ray::new(rec.p, Vec3::random_unit()), Event::Volume))
+            }
+            Self::HenyeyGreenstein(albedo, g) => Some((
+                albedo,
+                Ray::new(rec.p, henyey_greenstein(r_in.direction.unit(), g)),
+                Event::Volume,
+            )),
+            Self::Principled(ref principled) => principled.scatter(r_in, rec),
+            //the hit's shading normal was already perturbed when it was built
+            Self::Detailed(ref material, _) | Self::Identified(ref material, _) => {
+                material.scatter(r_in, rec)
             }
+            Self::DiffuseLight(_) => None,
         }
     }
+
+    //for a direction wi that light arrives from, the light that leaves along the ray, split by the kind of
+    //event that sends it there, and the chance density of `scatter` picking wi. Materials that only scatter
+    //into a few exact directions give None, since light from anywhere else never reaches the ray
+    pub(crate) fn evaluate(
+        &self,
+        r_in: Ray,
+        rec: &HitRecord,
+        wi: Vec3,
+    ) -> Option<(Vec<(Colour, Event)>, f64)> {
+        let wi = wi.unit();
+        match *self {
+            Self::Lambertian(albedo) => {
+                let cosine = Vec3::dot(wi, rec.normal).max(0.0);
+                Some((vec![(albedo * cosine / PI, Event::Diffuse)], cosine / PI))
+            }
+            Self::Isotropic(albedo) => {
+                Some((vec![(albedo / (4.0 * PI), Event::Volume)], 1.0 / (4.0 * PI)))
+            }
+            Self::HenyeyGreenstein(albedo, g) => {
+                let cosine = Vec3::dot(r_in.direction.unit(), wi);
+                let phase = (1.0 - g * g) / (4.0 * PI * (1.0 + g * g - 2.0 * g * cosine).powf(1.5));
+                Some((vec![(albedo * phase, Event::Volume)], phase))
+            }
+            Self::Principled(ref principled) => {
+                let (lobes, pdf) = principled.evaluate(r_in, rec, wi);
+                let events = [Event::Diffuse, Event::Specular, Event::Transmission];
+                Some((lobes.iter().copied().zip(events).collect(), pdf))
+            }
+            Self::Metal(albedo, roughness) => {
+                Some(evaluate_microfacet(r_in, rec, wi, roughness, |cosine| {
+                    schlick_metal(albedo, cosine)
+                }))
+            }
+            Self::Conductor(eta, k, roughness) => {
+                Some(evaluate_microfacet(r_in, rec, wi, roughness, |cosine| {
+                    conductor(eta, k, cosine)
+                }))
+            }
+            Self::RoughDielectric(ir, roughness, tint) => {
+                let frame = Frame::new(rec.normal);
+                let (wo, wi) = (frame.to_local(-r_in.direction.unit()), frame.to_local(wi));
+                let alpha = microfacet::alpha(roughness);
+                let refraction_ratio = if rec.front_face { ir.recip() } else { ir };
+                let none = (Colour::default(), Event::Specular);
+                if wo.e[2] <= 0.0 || wi.e[2] == 0.0 {
+                    return Some((vec![none], 0.0));
+                }
+
+                //the chance of `scatter` picking the microfacet normal between wo and wi, then reflecting
+                //or refracting off it, over how much a change in that normal moves wi
+                let reflect = wi.e[2] > 0.0;
+                let h = match reflect {
+                    true => (wo + wi).unit(),
+                    false => -(refraction_ratio * wo + wi),
+                };
+                if h.is_near_zero() {
+                    return Some((vec![none], 0.0));
+                }
+                let h = if h.e[2] < 0.0 { -h.unit() } else { h.unit() };
+                let (cos_o, cos_i) = (Vec3::dot(wo, h), Vec3::dot(wi, h));
+                if cos_o <= 0.0 || (cos_i > 0.0) != reflect {
+                    return Some((vec![none], 0.0));
+                }
+                let fresnel = microfacet::fresnel_dielectric(cos_o, refraction_ratio);
+                let visible_normal_pdf =
+                    microfacet::g1(wo, alpha) * cos_o * microfacet::d(h, alpha) / wo.e[2];
+                let (pdf, event) = match reflect {
+                    true => (
+                        fresnel * visible_normal_pdf / (4.0 * cos_o),
+                        Event::Specular,
+                    ),
+                    false => {
+                        let denominator = refraction_ratio * cos_o + cos_i;
+                        let jacobian = cos_i.abs() / (denominator * denominator);
+                        (
+                            (1.0 - fresnel) * visible_normal_pdf * jacobian,
+                            Event::Transmission,
+                        )
+                    }
+                };
+
+                //`scatter` weighs what it picks by G2 / G1, so the light that arrives is that much of the pdf
+                let weight = microfacet::g2(wo, wi, alpha) / microfacet::g1(wo, alpha);
+                Some((
+                    vec![(pdf * weight * beer_lambert(r_in, rec, tint), event)],
+                    pdf,
+                ))
+            }
+            Self::Detailed(ref material, _) | Self::Identified(ref material, _) => {
+                material.evaluate(r_in, rec, wi)
+            }
+            //perfectly smooth glass is left to find lights by scattering
+            _ => None,
+        }
+    }
+
+    //the overall colour of the surface, without lighting or view-dependent sheen, as compositing and denoising
+    //passes expect; clear dielectrics are white, and lights their colour at full brightness
+    pub fn albedo(&self, rec: &HitRecord) -> Colour {
+        match *self {
+            Self::Lambertian(albedo)
+            | Self::Metal(albedo, _)
+            | Self::Isotropic(albedo)
+            | Self::HenyeyGreenstein(albedo, _) => albedo,
+            //reflectance at normal incidence
+            Self::Conductor(eta, k, _) => Colour::new(
+                microfacet::fresnel_conductor(1.0, eta.e[0], k.e[0]),
+                microfacet::fresnel_conductor(1.0, eta.e[1], k.e[1]),
+                microfacet::fresnel_conductor(1.0, eta.e[2], k.e[2]),
+            ),
+            Self::Dielectric(_) | Self::RoughDielectric(..) => Colour::new(1.0, 1.0, 1.0),
+            Self::Principled(ref principled) => principled.base_colour.value(rec),
+            Self::Detailed(ref material, _) | Self::Identified(ref material, _) => {
+                material.albedo(rec)
+            }
+            Self::DiffuseLight(radiance) => {
+                let brightest = radiance.e[0].max(radiance.e[1]).max(radiance.e[2]);
+                match brightest > 0.0 {
+                    true => radiance / brightest,
+                    false => radiance,
+                }
+            }
+        }
+    }
+
+    //radiance given off from the hit towards where the ray came from
+    pub fn emitted(&self, rec: &HitRecord) -> Colour {
+        match *self {
+            Self::DiffuseLight(radiance) if rec.front_face => radiance,
+            Self::Detailed(ref material, _) | Self::Identified(ref material, _) => {
+                material.emitted(rec)
+            }
+            _ => Colour::default(),
+        }
+    }
+}
+
+//reflects off a GGX microfacet normal sampled from those visible to the incoming ray.
+//For this sampling strategy the throughput reduces to F * G2 / G1, no pdf required.
+fn scatter_microfacet<F: Fn(f64) -> Colour>(
+    r_in: Ray,
+    rec: &HitRecord,
+    roughness: f64,
+    fresnel: F,
+) -> Option<(Colour, Ray, Event)> {
+    let frame = Frame::new(rec.normal);
+    let wo = frame.to_local(-r_in.direction.unit());
+    if wo.e[2] <= 0.0 {
+        return None;
+    }
+
+    let alpha = microfacet::alpha(roughness);
+    let h = microfacet::sample_visible_normal(wo, alpha);
+    let wi = (-wo).reflect(h);
+    //the microfacet reflected the ray into the surface; single-scattering GGX loses this energy
+    if wi.e[2] <= 0.0 {
+        return None;
+    }
+
+    let weight = microfacet::g2(wo, wi, alpha) / microfacet::g1(wo, alpha);
+    Some((
+        fresnel(Vec3::dot(wi, h)) * weight,
+        Ray::new(rec.p, frame.to_world(wi)),
+        Event::Specular,
+    ))
+}
+
+//the light a rough reflector sends back along the ray from wi, with the chance `scatter_microfacet` picks wi
+fn evaluate_microfacet<F: Fn(f64) -> Colour>(
+    r_in: Ray,
+    rec: &HitRecord,
+    wi: Vec3,
+    roughness: f64,
+    fresnel: F,
+) -> (Vec<(Colour, Event)>, f64) {
+    let frame = Frame::new(rec.normal);
+    let (wo, wi) = (frame.to_local(-r_in.direction.unit()), frame.to_local(wi));
+    if wo.e[2] <= 0.0 || wi.e[2] <= 0.0 {
+        return (vec![(Colour::default(), Event::Specular)], 0.0);
+    }
+
+    let alpha = microfacet::alpha(roughness);
+    let h = (wo + wi).unit();
+    let d = microfacet::d(h, alpha);
+    //the bsdf times the cosine at wi, and visible normals turned into reflected directions
+    let reflected = fresnel(Vec3::dot(wi, h)) * d * microfacet::g2(wo, wi, alpha) / (4.0 * wo.e[2]);
+    let pdf = microfacet::g1(wo, alpha) * d / (4.0 * wo.e[2]);
+    (vec![(reflected, Event::Specular)], pdf)
+}
+
+//schlick's approximation, treating the albedo as the reflectance at normal incidence
+fn schlick_metal(albedo: Colour, cosine: f64) -> Colour {
+    albedo + (Colour::new(1.0, 1.0, 1.0) - albedo) * (1.0 - cosine).powi(5)
+}
+
+//the exact reflectance of a conductor, channel by channel
+fn conductor(eta: Colour, k: Colour, cosine: f64) -> Colour {
+    Colour::new(
+        microfacet::fresnel_conductor(cosine, eta.e[0], k.e[0]),
+        microfacet::fresnel_conductor(cosine, eta.e[1], k.e[1]),
+        microfacet::fresnel_conductor(cosine, eta.e[2], k.e[2]),
+    )
+}
+
+//light left after travelling from the ray's origin to this hit through a tinted medium;
+//a hit on the inside of a surface means the ray crossed the medium to get there
+fn beer_lambert(r_in: Ray, rec: &HitRecord, tint: Colour) -> Colour {
+    if rec.front_face {
+        return Colour::new(1.0, 1.0, 1.0);
+    }
+    let distance = rec.t * r_in.direction.length();
+    Colour::new(
+        tint.e[0].powf(distance),
+        tint.e[1].powf(distance),
+        tint.e[2].powf(distance),
+    )
 }
 
 //imprecise but reasonably accurate hack for dielectric reflectance dependant on incident angle
@@ -102,3 +553,137 @@ fn schlick_reflectance(cosine: f64, ref_idx: f64) -> f64 {
     let r0 = ((1.0 - ref_idx) / (1.0 + ref_idx)).powi(2);
     r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
 }
+
+//samples a direction from the Henyey-Greenstein phase function around the incoming direction
+fn henyey_greenstein(incoming: Vec3, g: f64) -> Vec3 {
+    let mut rng = rand::thread_rng();
+    let xi: f64 = rng.gen();
+    let cos_theta = if g.abs() < 1e-3 {
+        1.0 - 2.0 * xi
+    } else {
+        let sqr_term = (1.0 - g * g) / (1.0 - g + 2.0 * g * xi);
+        (1.0 + g * g - sqr_term * sqr_term) / (2.0 * g)
+    };
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * std::f64::consts::PI * rng.gen::<f64>();
+
+    let (tangent, bitangent) = incoming.basis();
+    sin_theta * phi.cos() * tangent + sin_theta * phi.sin() * bitangent + cos_theta * incoming
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ray::{Hittable, Sphere},
+        texture::Texture,
+        Point,
+    };
+
+    const N: usize = 100_000;
+
+    //each direction `scatter` picks must be one `evaluate` gives the same weight to once divided by its pdf;
+    //with a rough enough surface to integrate evenly, that pdf should also cover as much of the sphere as
+    //`scatter` reaches
+    fn assert_evaluate_matches_scatter(material: Material, from_inside: bool, check_pdf: bool) {
+        let sphere = Sphere::new(Point::default(), 1.0, material.clone());
+        let ray = match from_inside {
+            false => Ray::new(Point::new(0.0, 0.0, 3.0), Vec3::new(0.3, 0.1, -1.0)),
+            true => Ray::new(Point::new(0.1, 0.0, 0.0), Vec3::new(0.3, 0.1, -1.0)),
+        };
+        let rec = sphere.hit(ray, 0.001, f64::INFINITY).unwrap();
+
+        let mut reached = 0;
+        for _ in 0..N {
+            let (attenuation, scattered, event) = match material.scatter(ray, &rec) {
+                Some(scatter) => scatter,
+                None => continue,
+            };
+            reached += 1;
+            let (lobes, pdf) = material.evaluate(ray, &rec, scattered.direction).unwrap();
+            assert!(pdf > 0.0);
+            let weight = lobes[0].0 / pdf;
+            assert!(lobes[0].1 == event);
+            assert!((weight - attenuation).length() < 1e-6 * attenuation.length().max(1.0));
+        }
+        if !check_pdf {
+            return;
+        }
+
+        let mut pdf_total = 0.0;
+        for _ in 0..N {
+            pdf_total += material.evaluate(ray, &rec, Vec3::random_unit()).unwrap().1;
+        }
+        //uniform directions are picked with density 1 / 4pi
+        let covered = pdf_total * 4.0 * PI / N as f64;
+        let expected = reached as f64 / N as f64;
+        assert!(
+            (covered - expected).abs() < 0.05,
+            "the pdf covers {} of the sphere, but scatter reaches {}",
+            covered,
+            expected
+        );
+    }
+
+    #[test]
+    fn rough_reflectors_evaluate_what_they_scatter() {
+        for roughness in [0.3, 0.6, 1.0] {
+            let check_pdf = roughness == 1.0;
+            let metal = Material::Metal(Colour::new(0.9, 0.6, 0.2), roughness);
+            assert_evaluate_matches_scatter(metal, false, check_pdf);
+            assert_evaluate_matches_scatter(Material::gold(roughness), false, check_pdf);
+        }
+    }
+
+    #[test]
+    fn rough_glass_evaluates_what_it_scatters() {
+        for roughness in [0.3, 0.6, 1.0] {
+            let glass = Material::RoughDielectric(1.5, roughness, Colour::new(1.0, 1.0, 1.0));
+            let check_pdf = roughness == 1.0;
+            assert_evaluate_matches_scatter(glass.clone(), false, check_pdf);
+            assert_evaluate_matches_scatter(glass, true, check_pdf);
+        }
+    }
+
+    #[test]
+    fn principled_events_carry_only_their_own_lobe() {
+        let principled = Principled {
+            base_colour: Texture::Solid(Colour::new(0.8, 0.4, 0.2)),
+            metallic: 0.2,
+            roughness: 0.5,
+            clearcoat: 1.0,
+            transmission: 0.4,
+            ..Default::default()
+        };
+        let material = Material::Principled(principled);
+        let sphere = Sphere::new(Point::default(), 1.0, material.clone());
+        let ray = Ray::new(Point::new(0.0, 0.0, 3.0), Vec3::new(0.3, 0.1, -1.0));
+        let rec = sphere.hit(ray, 0.001, f64::INFINITY).unwrap();
+
+        //what scatter gives each event, against each lobe evaluated over the same directions
+        let events = [Event::Diffuse, Event::Specular, Event::Transmission];
+        let (mut scattered, mut evaluated) = ([Colour::default(); 3], [Colour::default(); 3]);
+        for _ in 0..N {
+            let (attenuation, direction, event) = match material.scatter(ray, &rec) {
+                Some((attenuation, scattered, event)) => (attenuation, scattered.direction, event),
+                None => continue,
+            };
+            let k = events.iter().position(|&e| e == event).unwrap();
+            scattered[k] = scattered[k] + attenuation / N as f64;
+            let (lobes, pdf) = material.evaluate(ray, &rec, direction).unwrap();
+            for (lobe, event) in lobes {
+                let k = events.iter().position(|&e| e == event).unwrap();
+                evaluated[k] = evaluated[k] + lobe / pdf / N as f64;
+            }
+        }
+        for k in 0..3 {
+            assert!(
+                (scattered[k] - evaluated[k]).length() < 0.03 * evaluated[k].length().max(0.1),
+                "{:?}: scatter gives {:?}, but the lobe is {:?}",
+                events[k],
+                scattered[k],
+                evaluated[k]
+            );
+        }
+    }
+}
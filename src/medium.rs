@@ -0,0 +1,458 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+    sync::Arc,
+};
+
+use rand::Rng;
+
+use crate::{
+    aabb::Aabb,
+    material::{Colour, Material},
+    ray::{HitRecord, Hittable, Ray, Sphere},
+    Point, Vec3,
+};
+
+//a volume of uniform density (smoke, mist, murky liquid) filling a closed boundary
+#[derive(Clone)]
+pub struct ConstantMedium {
+    boundary: Arc<dyn Hittable>,
+    density: f64,
+    phase_function: Material, //Isotropic or HenyeyGreenstein
+}
+impl ConstantMedium {
+    pub fn new<H: Hittable + 'static>(boundary: H, density: f64, phase_function: Material) -> Self {
+        Self {
+            boundary: Arc::new(boundary),
+            density,
+            phase_function,
+        }
+    }
+
+    //where the ray is inside the boundary between t_min and t_max
+    fn span(&self, r: Ray, t_min: f64, t_max: f64) -> Option<(f64, f64)> {
+        //search the whole line so rays that start inside the medium still find where they entered
+        let entry = self.boundary.hit(r, f64::NEG_INFINITY, f64::INFINITY)?;
+        let exit = self.boundary.hit(r, entry.t + 0.0001, f64::INFINITY)?;
+
+        let (entry, exit) = (entry.t.max(t_min), exit.t.min(t_max));
+        (entry < exit).then_some((entry, exit))
+    }
+}
+impl Hittable for ConstantMedium {
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let (entry, exit) = self.span(r, t_min, t_max)?;
+        let distance_inside = (exit - entry) * r.direction.length();
+        let hit_distance = free_path(self.density);
+        if hit_distance > distance_inside {
+            return None;
+        }
+
+        let t = entry + hit_distance / r.direction.length();
+        Some(HitRecord {
+            t,
+            p: r.at(t),
+            normal: Vec3::new(1.0, 0.0, 0.0), //arbitrary, phase functions don't use it
+            geometric_normal: Vec3::new(1.0, 0.0, 0.0),
+            material: &self.phase_function,
+            front_face: true,
+            emitted: Colour::default(),
+            u: 0.0,
+            v: 0.0,
+            tangent: Vec3::new(0.0, 1.0, 0.0),
+            bitangent: Vec3::new(0.0, 0.0, 1.0),
+            vertex_colour: None,
+            undetailed_normal: None,
+            object_id: None,
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.boundary.bounding_box()
+    }
+
+    //exactly, by Beer-Lambert
+    fn transmittance(&self, r: Ray, t_min: f64, t_max: f64) -> f64 {
+        let (entry, exit) = match self.span(r, t_min, t_max) {
+            Some(span) => span,
+            None => return 1.0,
+        };
+        (-self.density * (exit - entry) * r.direction.length()).exp()
+    }
+}
+
+//a homogeneous atmosphere filling all space between surfaces, out to `extent` from the world origin
+#[derive(Clone)]
+pub struct Fog {
+    medium: ConstantMedium,
+}
+impl Fog {
+    pub fn new(density: f64, extent: f64, phase_function: Material) -> Self {
+        Self {
+            medium: ConstantMedium::new(
+                Sphere::new(Point::default(), extent, phase_function.clone()),
+                density,
+                phase_function,
+            ),
+        }
+    }
+
+    //scatters the ray somewhere before t_max, or lets it through to whatever lies beyond
+    pub fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        self.medium.hit(r, t_min, t_max)
+    }
+
+    pub fn transmittance(&self, r: Ray, t_min: f64, t_max: f64) -> f64 {
+        self.medium.transmittance(r, t_min, t_max)
+    }
+}
+
+//dense voxel grid of densities spanning an axis-aligned box, stored with x varying fastest
+#[derive(Clone)]
+pub struct DensityGrid {
+    bounds: Aabb,
+    resolution: [usize; 3],
+    data: Vec<f64>,
+    max: f64,
+}
+impl DensityGrid {
+    pub fn new(bounds: Aabb, resolution: [usize; 3], data: Vec<f64>) -> Self {
+        assert!(
+            resolution.iter().all(|&n| n >= 1),
+            "density grids need at least one voxel along each axis"
+        );
+        assert_eq!(
+            resolution.iter().product::<usize>(),
+            data.len(),
+            "density grid resolution doesn't match its data"
+        );
+        assert!(
+            data.iter().all(|d| d.is_finite()),
+            "density grids can't hold NaN or infinite densities"
+        );
+        //simulations often leave slightly negative densities behind, which would make tracking add light
+        let data: Vec<f64> = data.into_iter().map(|d| d.max(0.0)).collect();
+        let max = data.iter().cloned().fold(0.0, f64::max);
+        Self {
+            bounds,
+            resolution,
+            data,
+            max,
+        }
+    }
+
+    //samples a procedural density (noise, an analytic falloff, ...) at every voxel
+    pub fn from_fn<F: Fn(Point) -> f64>(bounds: Aabb, resolution: [usize; 3], density: F) -> Self {
+        assert!(
+            resolution.iter().all(|&n| n >= 1),
+            "density grids need at least one voxel along each axis"
+        );
+        let [nx, ny, nz] = resolution;
+        let mut data = Vec::with_capacity(nx * ny * nz);
+        for z in 0..nz {
+            for y in 0..ny {
+                for x in 0..nx {
+                    let local = Vec3::new(
+                        x as f64 / (nx - 1).max(1) as f64,
+                        y as f64 / (ny - 1).max(1) as f64,
+                        z as f64 / (nz - 1).max(1) as f64,
+                    );
+                    data.push(density(bounds.min + local * bounds.size()).max(0.0));
+                }
+            }
+        }
+        Self::new(bounds, resolution, data)
+    }
+
+    //headerless little-endian f32 values, x varying fastest
+    pub fn from_raw<P: AsRef<Path>>(
+        path: P,
+        bounds: Aabb,
+        resolution: [usize; 3],
+    ) -> io::Result<Self> {
+        if resolution.contains(&0) {
+            return Err(invalid_data(
+                "raw density grids need at least one voxel along each axis",
+            ));
+        }
+        let bytes = fs::read(path)?;
+        if bytes.len() != resolution.iter().product::<usize>() * 4 {
+            return Err(invalid_data(
+                "raw density grid size doesn't match its resolution",
+            ));
+        }
+        let data: Vec<f64> = bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64)
+            .collect();
+        if data.iter().any(|d| !d.is_finite()) {
+            return Err(invalid_data(
+                "raw density grid holds NaN or infinite densities",
+            ));
+        }
+        Ok(Self::new(bounds, resolution, data))
+    }
+
+    //a 3D .npy array of little-endian f32 or f64, indexed [z][y][x] in C order
+    pub fn from_npy<P: AsRef<Path>>(path: P, bounds: Aabb) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        if bytes.len() < 10 || &bytes[..6] != b"\x93NUMPY" {
+            return Err(invalid_data("not a .npy file"));
+        }
+
+        //version 1 has a u16 header length, later versions a u32
+        let (header_start, header_len) = match bytes[6] {
+            1 => (10, u16::from_le_bytes([bytes[8], bytes[9]]) as usize),
+            _ if bytes.len() >= 12 => (
+                12,
+                u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]) as usize,
+            ),
+            _ => return Err(invalid_data("truncated .npy header")),
+        };
+        let header = bytes
+            .get(header_start..header_start + header_len)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .ok_or_else(|| invalid_data("truncated .npy header"))?;
+
+        if header.contains("'fortran_order': True") {
+            return Err(invalid_data("fortran-ordered .npy arrays are unsupported"));
+        }
+        let width = if header.contains("'<f4'") {
+            4
+        } else if header.contains("'<f8'") {
+            8
+        } else {
+            return Err(invalid_data(".npy arrays must be little-endian f32 or f64"));
+        };
+
+        let shape: Vec<usize> = header
+            .split("'shape':")
+            .nth(1)
+            .and_then(|rest| rest.split(['(', ')']).nth(1))
+            .ok_or_else(|| invalid_data("missing .npy shape"))?
+            .split(',')
+            .map(str::trim)
+            .filter(|dim| !dim.is_empty())
+            .map(|dim| dim.parse().map_err(|_| invalid_data("bad .npy shape")))
+            .collect::<io::Result<_>>()?;
+        let resolution = match shape[..] {
+            [nz, ny, nx] => [nx, ny, nz],
+            _ => return Err(invalid_data(".npy density grids must be 3D")),
+        };
+        if resolution.contains(&0) {
+            return Err(invalid_data(".npy density grids can't be empty"));
+        }
+
+        let body = &bytes[header_start + header_len..];
+        if body.len() != resolution.iter().product::<usize>() * width {
+            return Err(invalid_data(".npy data size doesn't match its shape"));
+        }
+        let data: Vec<f64> = match width {
+            4 => body
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64)
+                .collect(),
+            _ => body
+                .chunks_exact(8)
+                .map(|b| f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]))
+                .collect(),
+        };
+        if data.iter().any(|d| !d.is_finite()) {
+            return Err(invalid_data(
+                ".npy density grid holds NaN or infinite densities",
+            ));
+        }
+        Ok(Self::new(bounds, resolution, data))
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        self.bounds
+    }
+
+    pub fn max(&self) -> f64 {
+        self.max
+    }
+
+    //trilinearly interpolated density, zero outside the grid
+    pub fn density(&self, p: Point) -> f64 {
+        let size = self.bounds.size();
+        let mut index = [0; 3];
+        let mut fract = [0.0; 3];
+        for axis in 0..3 {
+            let local = (p.e[axis] - self.bounds.min.e[axis]) / size.e[axis];
+            if !(0.0..=1.0).contains(&local) {
+                return 0.0;
+            }
+            let scaled = local * (self.resolution[axis] - 1) as f64;
+            index[axis] = (scaled as usize).min(self.resolution[axis].saturating_sub(2));
+            fract[axis] = scaled - index[axis] as f64;
+        }
+
+        let mut accum = 0.0;
+        for corner in 0..8 {
+            let mut weight = 1.0;
+            let mut voxel = [0; 3];
+            for axis in 0..3 {
+                let upper = (corner >> axis) & 1 == 1;
+                voxel[axis] = (index[axis] + upper as usize).min(self.resolution[axis] - 1);
+                weight *= if upper {
+                    fract[axis]
+                } else {
+                    1.0 - fract[axis]
+                };
+            }
+            accum += weight * self.voxel(voxel);
+        }
+        accum
+    }
+
+    fn voxel(&self, [x, y, z]: [usize; 3]) -> f64 {
+        self.data[x + self.resolution[0] * (y + self.resolution[1] * z)]
+    }
+}
+
+//a volume whose density varies through space (clouds, smoke simulations, fire)
+#[derive(Clone)]
+pub struct HeterogeneousMedium {
+    grid: Arc<DensityGrid>,
+    density_scale: f64,
+    phase_function: Material,
+    emission: Option<(Arc<DensityGrid>, Colour)>,
+}
+impl HeterogeneousMedium {
+    pub fn new(grid: DensityGrid, density_scale: f64, phase_function: Material) -> Self {
+        Self {
+            grid: Arc::new(grid),
+            density_scale,
+            phase_function,
+            emission: None,
+        }
+    }
+
+    //makes the volume glow wherever `intensity` (temperature, fuel, ...) is nonzero
+    pub fn with_emission(mut self, intensity: DensityGrid, colour: Colour) -> Self {
+        self.emission = Some((Arc::new(intensity), colour));
+        self
+    }
+
+    fn majorant(&self) -> f64 {
+        self.grid.max() * self.density_scale
+    }
+}
+impl Hittable for HeterogeneousMedium {
+    //delta (Woodcock) tracking: step through a fictitious homogeneous medium as dense as the densest voxel,
+    //and accept each tentative collision with probability proportional to the real density there
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let (mut t, t_exit) = self.grid.bounds().intersect(r, t_min, t_max)?;
+        let majorant = self.majorant();
+        if majorant <= 0.0 {
+            return None;
+        }
+
+        let ray_length = r.direction.length();
+        let mut rng = rand::thread_rng();
+        loop {
+            t += free_path(majorant) / ray_length;
+            if t >= t_exit {
+                return None;
+            }
+
+            let p = r.at(t);
+            if rng.gen::<f64>() * majorant < self.grid.density(p) * self.density_scale {
+                return Some(HitRecord {
+                    t,
+                    p,
+                    normal: Vec3::new(1.0, 0.0, 0.0),
+                    geometric_normal: Vec3::new(1.0, 0.0, 0.0),
+                    material: &self.phase_function,
+                    front_face: true,
+                    emitted: match &self.emission {
+                        Some((intensity, colour)) => intensity.density(p) * *colour,
+                        None => Colour::default(),
+                    },
+                    u: 0.0,
+                    v: 0.0,
+                    tangent: Vec3::new(0.0, 1.0, 0.0),
+                    bitangent: Vec3::new(0.0, 0.0, 1.0),
+                    vertex_colour: None,
+                    undetailed_normal: None,
+                    object_id: None,
+                });
+            }
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.grid.bounds())
+    }
+
+    //unbiased estimate of the fraction of light passing through the volume between t_min and t_max, via ratio tracking
+    fn transmittance(&self, r: Ray, t_min: f64, t_max: f64) -> f64 {
+        let (mut t, t_exit) = match self.grid.bounds().intersect(r, t_min, t_max) {
+            Some(interval) => interval,
+            None => return 1.0,
+        };
+        let majorant = self.majorant();
+        if majorant <= 0.0 {
+            return 1.0;
+        }
+
+        let ray_length = r.direction.length();
+        let mut transmittance = 1.0;
+        loop {
+            t += free_path(majorant) / ray_length;
+            if t >= t_exit {
+                return transmittance;
+            }
+            transmittance *= 1.0 - self.grid.density(r.at(t)) * self.density_scale / majorant;
+        }
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
+
+//samples the distance travelled before a collision, with probability exp(-density * distance)
+fn free_path(density: f64) -> f64 {
+    -(1.0 - rand::thread_rng().gen::<f64>()).ln() / density
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_box() -> Aabb {
+        Aabb::new(Point::new(0.0, 0.0, 0.0), Point::new(1.0, 1.0, 1.0))
+    }
+
+    #[test]
+    fn negative_densities_dont_let_light_through_brighter() {
+        //a checkerboard of dense and negative voxels, as advected simulations leave behind
+        let data = (0..8)
+            .map(|i| if i % 2 == 0 { 4.0 } else { -3.0 })
+            .collect();
+        let grid = DensityGrid::new(unit_box(), [2, 2, 2], data);
+        assert_eq!(grid.max(), 4.0);
+        let medium = HeterogeneousMedium::new(grid, 1.0, Material::Isotropic(Colour::default()));
+        let ray = Ray::new(Point::new(-1.0, 0.3, 0.6), Vec3::new(1.0, 0.0, 0.0));
+        for _ in 0..1000 {
+            let transmittance = medium.transmittance(ray, 0.0, f64::INFINITY);
+            assert!((0.0..=1.0).contains(&transmittance));
+        }
+    }
+
+    #[test]
+    fn raw_grids_with_nan_are_rejected() {
+        let path = std::env::temp_dir().join("raytracer_nan_density.raw");
+        let bytes: Vec<u8> = [1.0, f32::NAN]
+            .iter()
+            .flat_map(|d| d.to_le_bytes())
+            .collect();
+        fs::write(&path, bytes).unwrap();
+        let result = DensityGrid::from_raw(&path, unit_box(), [2, 1, 1]);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(result.err().unwrap().kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,321 @@
+use std::{error::Error, fmt, fs, io, path::Path};
+
+use crate::{
+    aabb::Aabb,
+    bvh::Bvh,
+    material::{Colour, Material},
+    ply,
+    ray::{HitRecord, Hittable, Ray},
+    stl,
+    texture::Cutout,
+    transform::Transform,
+    Point, Vec3,
+};
+
+//why a mesh file couldn't be loaded
+#[derive(Debug)]
+pub enum MeshError {
+    Io(io::Error),
+    Header(String),                //a missing, malformed or unsupported header
+    Parse(usize, String),          //ascii data that doesn't parse; its line number
+    Truncated,                     //the data ends before everything the header promised
+    IndexOutOfRange(usize, usize), //a face uses a vertex that doesn't exist; its index, and the vertex count
+}
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{}", error),
+            Self::Header(message) => write!(f, "bad header: {}", message),
+            Self::Parse(line, message) => write!(f, "line {}: {}", line, message),
+            Self::Truncated => write!(f, "the file ends early"),
+            Self::IndexOutOfRange(index, count) => {
+                write!(f, "vertex {} used, but there are only {}", index, count)
+            }
+        }
+    }
+}
+impl Error for MeshError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+impl From<io::Error> for MeshError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+//indexed triangles, optionally with per-vertex normals for smooth shading, texture coordinates and colours
+#[derive(Clone)]
+pub struct TriangleMesh {
+    positions: Vec<Point>,
+    indices: Vec<[usize; 3]>,
+    normals: Option<Vec<Vec3>>,
+    uvs: Option<Vec<(f64, f64)>>,
+    colours: Option<Vec<Colour>>,
+    material: Material,
+    cutout: Option<Cutout>,
+    bvh: Bvh,
+}
+impl TriangleMesh {
+    pub fn new(positions: Vec<Point>, indices: Vec<[usize; 3]>, material: Material) -> Self {
+        let boxes: Vec<Aabb> = indices
+            .iter()
+            .map(|triangle| Aabb::from_points(triangle.iter().map(|&i| positions[i])).unwrap())
+            .collect();
+        Self {
+            bvh: Bvh::new(&boxes),
+            positions,
+            indices,
+            normals: None,
+            uvs: None,
+            colours: None,
+            material,
+            cutout: None,
+        }
+    }
+
+    //like `new`, but an error rather than a panic for indices of vertices that don't exist
+    pub(crate) fn checked(
+        positions: Vec<Point>,
+        indices: Vec<[usize; 3]>,
+        material: Material,
+    ) -> Result<Self, MeshError> {
+        let count = positions.len();
+        if let Some(&index) = indices.iter().flatten().find(|&&i| i >= count) {
+            return Err(MeshError::IndexOutOfRange(index, count));
+        }
+        Ok(Self::new(positions, indices, material))
+    }
+
+    //ascii or binary PLY, with whatever normals, colours and texture coordinates its vertices carry
+    pub fn from_ply<P: AsRef<Path>>(path: P, material: Material) -> Result<Self, MeshError> {
+        ply::parse(&fs::read(path)?, material)
+    }
+
+    //ascii or binary STL; its facet normals are ignored in favour of the winding order
+    pub fn from_stl<P: AsRef<Path>>(path: P, material: Material) -> Result<Self, MeshError> {
+        stl::parse(&fs::read(path)?, material)
+    }
+
+    pub fn with_normals(mut self, normals: Vec<Vec3>) -> Self {
+        assert_eq!(normals.len(), self.positions.len(), "one normal per vertex");
+        self.normals = Some(normals);
+        self
+    }
+
+    pub fn with_uvs(mut self, uvs: Vec<(f64, f64)>) -> Self {
+        assert_eq!(uvs.len(), self.positions.len(), "one uv per vertex");
+        self.uvs = Some(uvs);
+        self
+    }
+
+    //linear colours, read by `Texture::VertexColour`
+    pub fn with_colours(mut self, colours: Vec<Colour>) -> Self {
+        assert_eq!(colours.len(), self.positions.len(), "one colour per vertex");
+        self.colours = Some(colours);
+        self
+    }
+
+    //masks out parts of triangles by their texture coordinates
+    pub fn with_cutout(mut self, cutout: Cutout) -> Self {
+        self.cutout = Some(cutout);
+        self
+    }
+
+    //moved into place, keeping its normals perpendicular and, through mirror images, its fronts outward
+    pub fn transformed(self, transform: &Transform) -> Self {
+        let positions = self.positions.iter().map(|&p| transform.point(p)).collect();
+        let mut indices = self.indices;
+        if transform.determinant() < 0.0 {
+            for triangle in &mut indices {
+                triangle.swap(1, 2);
+            }
+        }
+        Self {
+            normals: self.normals.map(|normals| {
+                normals
+                    .into_iter()
+                    .map(|n| transform.normal(n).unit())
+                    .collect()
+            }),
+            uvs: self.uvs,
+            colours: self.colours,
+            cutout: self.cutout,
+            ..Self::new(positions, indices, self.material)
+        }
+    }
+
+    fn uv(&self, triangle: usize, b1: f64, b2: f64) -> (f64, f64) {
+        let [i0, i1, i2] = self.indices[triangle];
+        let b0 = 1.0 - b1 - b2;
+        match &self.uvs {
+            Some(uvs) => (
+                b0 * uvs[i0].0 + b1 * uvs[i1].0 + b2 * uvs[i2].0,
+                b0 * uvs[i0].1 + b1 * uvs[i1].1 + b2 * uvs[i2].1,
+            ),
+            None => (b1, b2),
+        }
+    }
+
+    fn intersect(
+        &self,
+        triangle: usize,
+        r: Ray,
+        t_min: f64,
+        t_max: f64,
+    ) -> Option<(f64, f64, f64)> {
+        let [i0, i1, i2] = self.indices[triangle];
+        intersect_triangle(
+            [self.positions[i0], self.positions[i1], self.positions[i2]],
+            r,
+            t_min,
+            t_max,
+        )
+    }
+
+    fn record(&self, triangle: usize, r: Ray, t: f64, b1: f64, b2: f64) -> HitRecord<'_> {
+        let [i0, i1, i2] = self.indices[triangle];
+        let b0 = 1.0 - b1 - b2;
+        let p0 = self.positions[i0];
+        let edge1 = self.positions[i1] - p0;
+        let edge2 = self.positions[i2] - p0;
+
+        let geometric = Vec3::cross(edge1, edge2).unit();
+        let shading = match &self.normals {
+            Some(normals) => {
+                let interpolated = (b0 * normals[i0] + b1 * normals[i1] + b2 * normals[i2]).unit();
+                //vertex normals may disagree with the winding order; trust the winding order
+                match Vec3::dot(interpolated, geometric) < 0.0 {
+                    true => -interpolated,
+                    false => interpolated,
+                }
+            }
+            None => geometric,
+        };
+
+        let (u, v) = self.uv(triangle, b1, b2);
+        let (tangent, bitangent) = match &self.uvs {
+            Some(uvs) => {
+                let (uv0, uv1, uv2) = (uvs[i0], uvs[i1], uvs[i2]);
+                uv_tangents(
+                    shading,
+                    edge1,
+                    edge2,
+                    (uv1.0 - uv0.0, uv1.1 - uv0.1),
+                    (uv2.0 - uv0.0, uv2.1 - uv0.1),
+                )
+            }
+            None => shading.basis(),
+        };
+
+        let front_face = HitRecord::front_face(r, geometric);
+        let facing = if front_face { 1.0 } else { -1.0 };
+        HitRecord {
+            t,
+            p: r.at(t),
+            normal: facing * shading,
+            geometric_normal: facing * geometric,
+            material: &self.material,
+            front_face,
+            emitted: Colour::default(),
+            u,
+            v,
+            tangent,
+            bitangent,
+            vertex_colour: self
+                .colours
+                .as_ref()
+                .map(|colours| b0 * colours[i0] + b1 * colours[i1] + b2 * colours[i2]),
+            undetailed_normal: None,
+            object_id: None,
+        }
+        .with_detail()
+    }
+}
+impl Hittable for TriangleMesh {
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        self.bvh
+            .traverse(r, t_min, t_max, |triangle, t_max| {
+                let (t, b1, b2) = self.intersect(triangle, r, t_min, t_max)?;
+                if let Some(cutout) = &self.cutout {
+                    let (u, v) = self.uv(triangle, b1, b2);
+                    if !cutout.is_opaque(u, v) {
+                        return None;
+                    }
+                }
+                Some((t, (triangle, t, b1, b2)))
+            })
+            .map(|(triangle, t, b1, b2)| self.record(triangle, r, t, b1, b2))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.bvh.bounds()
+    }
+}
+
+//Möller-Trumbore; the distance along the ray and the barycentric weights of the second and third vertices
+pub(crate) fn intersect_triangle(
+    [p0, p1, p2]: [Point; 3],
+    r: Ray,
+    t_min: f64,
+    t_max: f64,
+) -> Option<(f64, f64, f64)> {
+    let edge1 = p1 - p0;
+    let edge2 = p2 - p0;
+
+    let pvec = Vec3::cross(r.direction, edge2);
+    let determinant = Vec3::dot(edge1, pvec);
+    if determinant.abs() < 1e-12 {
+        return None; //parallel to the triangle
+    }
+    let inv_determinant = determinant.recip();
+
+    let tvec = r.origin - p0;
+    let b1 = Vec3::dot(tvec, pvec) * inv_determinant;
+    if !(0.0..=1.0).contains(&b1) {
+        return None;
+    }
+    let qvec = Vec3::cross(tvec, edge1);
+    let b2 = Vec3::dot(r.direction, qvec) * inv_determinant;
+    if b2 < 0.0 || b1 + b2 > 1.0 {
+        return None;
+    }
+
+    let t = Vec3::dot(edge2, qvec) * inv_determinant;
+    if t < t_min || t > t_max {
+        return None;
+    }
+    Some((t, b1, b2))
+}
+
+//the directions in which u and v increase across a triangle, made perpendicular to the shading normal
+fn uv_tangents(
+    normal: Vec3,
+    edge1: Vec3,
+    edge2: Vec3,
+    duv1: (f64, f64),
+    duv2: (f64, f64),
+) -> (Vec3, Vec3) {
+    let determinant = duv1.0 * duv2.1 - duv2.0 * duv1.1;
+    if determinant.abs() < 1e-12 {
+        return normal.basis(); //degenerate texture coordinates
+    }
+    let dpdu = (duv2.1 * edge1 - duv1.1 * edge2) / determinant;
+    let dpdv = (duv1.0 * edge2 - duv2.0 * edge1) / determinant;
+
+    let tangent = dpdu - Vec3::dot(dpdu, normal) * normal;
+    if tangent.is_near_zero() {
+        return normal.basis();
+    }
+    let tangent = tangent.unit();
+    //mirrored texture coordinates flip the bitangent
+    let bitangent = Vec3::cross(normal, tangent);
+    match Vec3::dot(bitangent, dpdv) < 0.0 {
+        true => (tangent, -bitangent),
+        false => (tangent, bitangent),
+    }
+}
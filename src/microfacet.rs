@@ -0,0 +1,122 @@
+//Trowbridge-Reitz (GGX) microfacet distribution, shared by the rough materials.
+//Directions are in a local shading frame where the surface normal is +z.
+
+use std::f64::consts::PI;
+
+use rand::Rng;
+
+use crate::vec3::Vec3;
+
+//artists' roughness in [0, 1] to GGX alpha; squaring makes the scale perceptually even
+pub fn alpha(roughness: f64) -> f64 {
+    (roughness * roughness).max(1e-4)
+}
+
+//distribution of microfacet normals
+pub fn d(h: Vec3, alpha: f64) -> f64 {
+    let cos2 = h.e[2] * h.e[2];
+    let tan2 = (1.0 - cos2) / cos2;
+    let alpha2 = alpha * alpha;
+    alpha2 / (PI * cos2 * cos2 * (alpha2 + tan2).powi(2))
+}
+
+//Smith's auxiliary function for GGX
+fn lambda(w: Vec3, alpha: f64) -> f64 {
+    let cos2 = w.e[2] * w.e[2];
+    let tan2 = (1.0 - cos2).max(0.0) / cos2;
+    (-1.0 + (1.0 + alpha * alpha * tan2).sqrt()) / 2.0
+}
+
+//fraction of microfacets visible from w
+pub fn g1(w: Vec3, alpha: f64) -> f64 {
+    1.0 / (1.0 + lambda(w, alpha))
+}
+
+//height-correlated masking-shadowing
+pub fn g2(wo: Vec3, wi: Vec3, alpha: f64) -> f64 {
+    1.0 / (1.0 + lambda(wo, alpha) + lambda(wi, alpha))
+}
+
+//samples a microfacet normal visible from wo (Heitz, "Sampling the GGX Distribution of Visible Normals")
+pub fn sample_visible_normal(wo: Vec3, alpha: f64) -> Vec3 {
+    let mut rng = rand::thread_rng();
+
+    //stretch the view direction into the hemisphere configuration
+    let vh = Vec3::new(alpha * wo.e[0], alpha * wo.e[1], wo.e[2]).unit();
+    let len_sq = vh.e[0] * vh.e[0] + vh.e[1] * vh.e[1];
+    let t1 = if len_sq > 0.0 {
+        Vec3::new(-vh.e[1], vh.e[0], 0.0) / len_sq.sqrt()
+    } else {
+        Vec3::new(1.0, 0.0, 0.0)
+    };
+    let t2 = Vec3::cross(vh, t1);
+
+    //sample the projected area of the visible hemisphere
+    let r = rng.gen::<f64>().sqrt();
+    let phi = 2.0 * PI * rng.gen::<f64>();
+    let p1 = r * phi.cos();
+    let s = 0.5 * (1.0 + vh.e[2]);
+    let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+    let nh = p1 * t1 + p2 * t2 + (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt() * vh;
+
+    //unstretch
+    Vec3::new(alpha * nh.e[0], alpha * nh.e[1], nh.e[2].max(0.0)).unit()
+}
+
+//unpolarised reflectance of a conductor with complex index of refraction eta + ik
+pub fn fresnel_conductor(cos_i: f64, eta: f64, k: f64) -> f64 {
+    let cos2 = cos_i * cos_i;
+    let sin2 = 1.0 - cos2;
+    let t0 = eta * eta - k * k - sin2;
+    let a2_plus_b2 = (t0 * t0 + 4.0 * eta * eta * k * k).sqrt();
+    let t1 = a2_plus_b2 + cos2;
+    let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+    let t2 = 2.0 * cos_i * a;
+    let rs = (t1 - t2) / (t1 + t2);
+    let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+    0.5 * (rp + rs)
+}
+
+//unpolarised reflectance at an interface between dielectrics, eta being the incident over transmitted index
+pub fn fresnel_dielectric(cos_i: f64, eta: f64) -> f64 {
+    let sin2_t = eta * eta * (1.0 - cos_i * cos_i);
+    if sin2_t >= 1.0 {
+        return 1.0; //total internal reflection
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let rs = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let rp = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    0.5 * (rs * rs + rp * rp)
+}
+
+//converts between world space and the local shading frame around a normal
+#[derive(Clone, Copy)]
+pub struct Frame {
+    tangent: Vec3,
+    bitangent: Vec3,
+    normal: Vec3,
+}
+impl Frame {
+    pub fn new(normal: Vec3) -> Self {
+        let (tangent, bitangent) = normal.basis();
+        Self {
+            tangent,
+            bitangent,
+            normal,
+        }
+    }
+
+    pub fn to_local(self, v: Vec3) -> Vec3 {
+        Vec3::new(
+            Vec3::dot(v, self.tangent),
+            Vec3::dot(v, self.bitangent),
+            Vec3::dot(v, self.normal),
+        )
+    }
+
+    pub fn to_world(self, v: Vec3) -> Vec3 {
+        v.e[0] * self.tangent + v.e[1] * self.bitangent + v.e[2] * self.normal
+    }
+}
@@ -0,0 +1,82 @@
+use rand::seq::SliceRandom;
+
+use crate::{Point, Vec3};
+
+const POINT_COUNT: usize = 256;
+
+//gradient noise, as in Ken Perlin's "Improving Noise"; output is roughly in [-1, 1]
+#[derive(Clone)]
+pub struct Perlin {
+    gradients: Vec<Vec3>,
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>,
+}
+impl Default for Perlin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Perlin {
+    pub fn new() -> Self {
+        Self {
+            gradients: (0..POINT_COUNT).map(|_| Vec3::random_unit()).collect(),
+            perm_x: permutation(),
+            perm_y: permutation(),
+            perm_z: permutation(),
+        }
+    }
+
+    pub fn noise(&self, p: Point) -> f64 {
+        let floor = [p.e[0].floor(), p.e[1].floor(), p.e[2].floor()];
+        let fract = Vec3::new(p.e[0] - floor[0], p.e[1] - floor[1], p.e[2] - floor[2]);
+        let [i, j, k] = floor.map(|f| f as i64);
+
+        let mut accum = 0.0;
+        for di in 0..2 {
+            for dj in 0..2 {
+                for dk in 0..2 {
+                    let gradient = self.gradients[self.perm_x[((i + di) & 255) as usize]
+                        ^ self.perm_y[((j + dj) & 255) as usize]
+                        ^ self.perm_z[((k + dk) & 255) as usize]];
+                    let corner = Vec3::new(di as f64, dj as f64, dk as f64);
+
+                    //hermite smoothing of the weights hides the grid
+                    let weight = (0..3)
+                        .map(|axis| {
+                            let t = fract.e[axis] * fract.e[axis] * (3.0 - 2.0 * fract.e[axis]);
+                            match corner.e[axis] == 1.0 {
+                                true => t,
+                                false => 1.0 - t,
+                            }
+                        })
+                        .product::<f64>();
+                    accum += weight * Vec3::dot(gradient, fract - corner);
+                }
+            }
+        }
+
+        accum
+    }
+
+    //sum of octaves of noise at doubling frequency and halving amplitude
+    pub fn turbulence(&self, p: Point, depth: usize) -> f64 {
+        let mut accum = 0.0;
+        let mut p = p;
+        let mut weight = 1.0;
+
+        for _ in 0..depth {
+            accum += weight * self.noise(p);
+            weight *= 0.5;
+            p = p * 2.0;
+        }
+
+        accum.abs()
+    }
+}
+
+fn permutation() -> Vec<usize> {
+    let mut perm: Vec<usize> = (0..POINT_COUNT).collect();
+    perm.shuffle(&mut rand::thread_rng());
+    perm
+}
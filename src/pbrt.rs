@@ -0,0 +1,1016 @@
+//a practical subset of pbrt-v3 scene files: enough of the camera, shapes, materials and lights to render
+//most reference scenes recognisably, with warnings for whatever is left out
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    material::{Colour, Material},
+    mesh::{MeshError, TriangleMesh},
+    ray::{Camera, HittableList, PointLight, Projection, Sphere},
+    transform::Transform,
+    Point, Vec3, Viewport,
+};
+
+//everything needed to render a pbrt file, and what couldn't be carried over from it
+pub struct PbrtScene {
+    pub world: HittableList,
+    pub camera: Camera,
+    pub viewport: Viewport,
+    pub warnings: Vec<String>,
+}
+
+//why a pbrt file couldn't be imported
+#[derive(Debug)]
+pub enum PbrtError {
+    Io(io::Error),                 //the file, or one it includes, couldn't be read
+    Parse(PathBuf, usize, String), //malformed at a line of a file
+    Mesh(MeshError),               //a PLY file it refers to is broken
+}
+impl fmt::Display for PbrtError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{}", error),
+            Self::Parse(file, line, message) => {
+                write!(f, "{}:{}: {}", file.display(), line, message)
+            }
+            Self::Mesh(error) => write!(f, "{}", error),
+        }
+    }
+}
+impl Error for PbrtError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::Parse(..) => None,
+            Self::Mesh(error) => Some(error),
+        }
+    }
+}
+impl From<io::Error> for PbrtError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+impl From<MeshError> for PbrtError {
+    fn from(error: MeshError) -> Self {
+        Self::Mesh(error)
+    }
+}
+
+//reads a scene and the files it includes; unsupported directives and parameters become warnings
+pub fn load_pbrt<P: AsRef<Path>>(path: P) -> Result<PbrtScene, PbrtError> {
+    let mut importer = Importer::default();
+    importer.run(path.as_ref())?;
+    Ok(importer.finish())
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word(String), //directives
+    Text(String), //quoted strings
+    Number(f64),
+    Open,
+    Close,
+}
+
+fn tokenize(text: &str) -> Result<Vec<(usize, Token)>, (usize, String)> {
+    let mut tokens = vec![];
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            '#' => {
+                while chars.peek().is_some_and(|&c| c != '\n') {
+                    chars.next();
+                }
+            }
+            '[' => tokens.push((line, Token::Open)),
+            ']' => tokens.push((line, Token::Close)),
+            '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => text.extend(chars.next()),
+                        Some('\n') | None => return Err((line, "unterminated string".to_string())),
+                        Some(c) => text.push(c),
+                    }
+                }
+                tokens.push((line, Token::Text(text)));
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut word = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || matches!(c, '[' | ']' | '"' | '#') {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push((
+                    line,
+                    match word.parse() {
+                        Ok(number) => Token::Number(number),
+                        Err(_) => Token::Word(word),
+                    },
+                ));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+//one file's tokens and how far through them we are
+struct Tokens {
+    file: PathBuf,
+    list: Vec<(usize, Token)>,
+    next: usize,
+}
+impl Tokens {
+    fn line(&self) -> usize {
+        let at = self.next.min(self.list.len()).saturating_sub(1);
+        self.list.get(at).map_or(1, |&(line, _)| line)
+    }
+
+    fn error(&self, message: String) -> PbrtError {
+        PbrtError::Parse(self.file.clone(), self.line(), message)
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.list.get(self.next).map(|(_, token)| token)
+    }
+
+    fn take(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        self.next += 1;
+        token
+    }
+
+    fn number(&mut self) -> Result<f64, PbrtError> {
+        match self.take() {
+            Some(Token::Number(number)) => Ok(number),
+            other => Err(self.error(format!("expected a number, not {:?}", other))),
+        }
+    }
+
+    //a fixed number of them, optionally bracketed
+    fn numbers(&mut self, count: usize) -> Result<Vec<f64>, PbrtError> {
+        let bracketed = self.peek() == Some(&Token::Open);
+        if bracketed {
+            self.next += 1;
+        }
+        let numbers = (0..count)
+            .map(|_| self.number())
+            .collect::<Result<_, _>>()?;
+        if bracketed && self.take() != Some(Token::Close) {
+            return Err(self.error(format!("expected ] after {} numbers", count)));
+        }
+        Ok(numbers)
+    }
+
+    fn text(&mut self) -> Result<String, PbrtError> {
+        match self.take() {
+            Some(Token::Text(text)) => Ok(text),
+            other => Err(self.error(format!("expected a quoted string, not {:?}", other))),
+        }
+    }
+
+    //"type name" value pairs, up to the next directive
+    fn params(&mut self) -> Result<Params, PbrtError> {
+        let mut params = Params::default();
+        while let Some(Token::Text(declaration)) = self.peek().cloned() {
+            self.next += 1;
+            let (kind, name) = match declaration.split_whitespace().collect::<Vec<_>>()[..] {
+                [kind, name] => (kind.to_string(), name.to_string()),
+                _ => return Err(self.error(format!("bad parameter declaration {:?}", declaration))),
+            };
+            let values = match self.take() {
+                Some(Token::Open) => {
+                    let mut values = vec![];
+                    loop {
+                        match self.take() {
+                            Some(Token::Close) => break,
+                            Some(token @ Token::Number(_)) | Some(token @ Token::Text(_)) => {
+                                values.push(token)
+                            }
+                            other => {
+                                return Err(
+                                    self.error(format!("unexpected {:?} in {}", other, name))
+                                )
+                            }
+                        }
+                    }
+                    values
+                }
+                Some(token @ Token::Number(_)) | Some(token @ Token::Text(_)) => vec![token],
+                other => {
+                    return Err(
+                        self.error(format!("expected a value for {}, not {:?}", name, other))
+                    )
+                }
+            };
+            let value = if values.iter().all(|value| matches!(value, Token::Number(_))) {
+                Value::Numbers(
+                    values
+                        .into_iter()
+                        .filter_map(|value| match value {
+                            Token::Number(number) => Some(number),
+                            _ => None,
+                        })
+                        .collect(),
+                )
+            } else if values.iter().all(|value| matches!(value, Token::Text(_))) {
+                Value::Texts(
+                    values
+                        .into_iter()
+                        .filter_map(|value| match value {
+                            Token::Text(text) => Some(text),
+                            _ => None,
+                        })
+                        .collect(),
+                )
+            } else {
+                return Err(self.error(format!("{} mixes numbers and strings", name)));
+            };
+            params.list.push(Param { kind, name, value });
+        }
+        Ok(params)
+    }
+
+    //whatever belongs to a directive we don't understand
+    fn skip_arguments(&mut self) {
+        while matches!(self.peek(), Some(token) if !matches!(token, Token::Word(_))) {
+            self.next += 1;
+        }
+    }
+}
+
+enum Value {
+    Numbers(Vec<f64>),
+    Texts(Vec<String>),
+}
+
+struct Param {
+    kind: String,
+    name: String,
+    value: Value,
+}
+
+//a directive's parameters, removed as they're used so the rest can be reported
+#[derive(Default)]
+struct Params {
+    list: Vec<Param>,
+    problems: Vec<String>,
+}
+impl Params {
+    fn take(&mut self, name: &str) -> Option<Param> {
+        let index = self.list.iter().position(|param| param.name == name)?;
+        Some(self.list.remove(index))
+    }
+
+    fn numbers(&mut self, name: &str) -> Option<Vec<f64>> {
+        match self.take(name)?.value {
+            Value::Numbers(numbers) => Some(numbers),
+            Value::Texts(_) => {
+                self.problems.push(format!("{} should be numbers", name));
+                None
+            }
+        }
+    }
+
+    fn float(&mut self, name: &str, default: f64) -> f64 {
+        self.numbers(name)
+            .and_then(|numbers| numbers.first().copied())
+            .unwrap_or(default)
+    }
+
+    fn text(&mut self, name: &str) -> Option<String> {
+        match self.take(name)?.value {
+            Value::Texts(texts) => texts.into_iter().next(),
+            Value::Numbers(_) => {
+                self.problems.push(format!("{} should be a string", name));
+                None
+            }
+        }
+    }
+
+    fn bool(&mut self, name: &str, default: bool) -> bool {
+        self.text(name).map_or(default, |text| text == "true")
+    }
+
+    //only RGB is understood; spectra, blackbodies and textures fall back to the default
+    fn colour(&mut self, name: &str, default: Colour) -> Colour {
+        let param = match self.take(name) {
+            Some(param) => param,
+            None => return default,
+        };
+        match (param.kind.as_str(), param.value) {
+            ("rgb" | "color", Value::Numbers(rgb)) if rgb.len() == 3 => {
+                Colour::new(rgb[0], rgb[1], rgb[2])
+            }
+            (kind, _) => {
+                self.problems.push(format!(
+                    "{} {} isn't supported, so it's left at its default",
+                    kind, name
+                ));
+                default
+            }
+        }
+    }
+
+    fn finish(self, context: &str, warnings: &mut Vec<String>) {
+        for problem in self.problems {
+            warnings.push(format!("{}: {}", context, problem));
+        }
+        for param in self.list {
+            warnings.push(format!(
+                "{}: ignoring \"{} {}\"",
+                context, param.kind, param.name
+            ));
+        }
+    }
+}
+
+//what shapes pick up from the attributes around them
+#[derive(Clone, Default)]
+struct State {
+    transform: Transform, //from object space to pbrt's world space
+    material: Material,
+    area_light: Option<Colour>,
+}
+
+struct CameraSettings {
+    camera_from_world: Transform,
+    projection: Projection,
+    fov: f64,
+    lens_radius: f64,
+    focal_distance: f64,
+    aspect_ratio: Option<f64>,
+}
+
+#[derive(Default)]
+struct Importer {
+    world: HittableList,
+    warnings: Vec<String>,
+    state: State,
+    attributes: Vec<State>,
+    transforms: Vec<Transform>,
+    named_materials: HashMap<String, Material>,
+    materials: usize, //how many have been declared, to number them in the material ID pass
+    coordinate_systems: HashMap<String, Transform>,
+    camera: Option<CameraSettings>,
+    resolution: Option<(usize, usize)>,
+    samples: Option<usize>,
+    max_depth: Option<usize>,
+    background: Colour,
+    in_object: bool,
+}
+impl Importer {
+    fn run(&mut self, path: &Path) -> Result<(), PbrtError> {
+        let text = fs::read_to_string(path)?;
+        let list = tokenize(&text)
+            .map_err(|(line, message)| PbrtError::Parse(path.to_path_buf(), line, message))?;
+        let mut tokens = Tokens {
+            file: path.to_path_buf(),
+            list,
+            next: 0,
+        };
+        let base = path.parent().map(Path::to_path_buf).unwrap_or_default();
+
+        while let Some(token) = tokens.take() {
+            let directive = match token {
+                Token::Word(directive) => directive,
+                other => return Err(tokens.error(format!("expected a directive, not {:?}", other))),
+            };
+            let context = format!("{}:{}", path.display(), tokens.line());
+            match directive.as_str() {
+                "Include" => {
+                    let file = tokens.text()?;
+                    self.run(&base.join(file))?;
+                }
+                "WorldBegin" => self.state.transform = Transform::identity(),
+                "WorldEnd" => {}
+                "AttributeBegin" => self.attributes.push(self.state.clone()),
+                "AttributeEnd" => match self.attributes.pop() {
+                    Some(state) => self.state = state,
+                    None => self
+                        .warnings
+                        .push(format!("{}: AttributeEnd without AttributeBegin", context)),
+                },
+                "TransformBegin" => self.transforms.push(self.state.transform),
+                "TransformEnd" => match self.transforms.pop() {
+                    Some(transform) => self.state.transform = transform,
+                    None => self
+                        .warnings
+                        .push(format!("{}: TransformEnd without TransformBegin", context)),
+                },
+
+                //each transformation applies before those already in place, so they compose inside out
+                "Identity" => self.state.transform = Transform::identity(),
+                "Translate" => {
+                    let n = tokens.numbers(3)?;
+                    self.concatenate(Transform::translate(Vec3::new(n[0], n[1], n[2])));
+                }
+                "Scale" => {
+                    let n = tokens.numbers(3)?;
+                    self.concatenate(Transform::scale(Vec3::new(n[0], n[1], n[2])));
+                }
+                "Rotate" => {
+                    let n = tokens.numbers(4)?;
+                    self.concatenate(Transform::rotate(Vec3::new(n[1], n[2], n[3]), n[0]));
+                }
+                "LookAt" => {
+                    let n = tokens.numbers(9)?;
+                    match look_at(
+                        Point::new(n[0], n[1], n[2]),
+                        Point::new(n[3], n[4], n[5]),
+                        Vec3::new(n[6], n[7], n[8]),
+                    ) {
+                        Some(transform) => self.concatenate(transform),
+                        None => self.warnings.push(format!(
+                            "{}: LookAt's up is along its view, so it's ignored",
+                            context
+                        )),
+                    }
+                }
+                "Transform" => self.state.transform = columns(&tokens.numbers(16)?),
+                "ConcatTransform" => {
+                    let transform = columns(&tokens.numbers(16)?);
+                    self.concatenate(transform);
+                }
+                "CoordinateSystem" => {
+                    let name = tokens.text()?;
+                    self.coordinate_systems.insert(name, self.state.transform);
+                }
+                "CoordSysTransform" => {
+                    let name = tokens.text()?;
+                    match self.coordinate_systems.get(&name) {
+                        Some(&transform) => self.state.transform = transform,
+                        None => self
+                            .warnings
+                            .push(format!("{}: no coordinate system {:?}", context, name)),
+                    }
+                }
+
+                "Camera" => {
+                    let kind = tokens.text()?;
+                    let mut params = tokens.params()?;
+                    let projection = match kind.as_str() {
+                        "perspective" => Projection::Perspective,
+                        "orthographic" => Projection::Orthographic(2.0),
+                        "environment" => {
+                            self.warnings.push(format!(
+                                "{}: environment cameras are centred on where they look, not laid out as in pbrt",
+                                context
+                            ));
+                            Projection::Equirectangular
+                        }
+                        other => {
+                            self.warnings.push(format!(
+                                "{}: {} cameras aren't supported; using a perspective one",
+                                context, other
+                            ));
+                            Projection::Perspective
+                        }
+                    };
+                    let camera_from_world = self.state.transform;
+                    if let Some(world_from_camera) = camera_from_world.inverse() {
+                        self.coordinate_systems
+                            .insert("camera".to_string(), world_from_camera);
+                    }
+                    self.camera = Some(CameraSettings {
+                        camera_from_world,
+                        projection,
+                        fov: params.float("fov", 90.0),
+                        lens_radius: params.float("lensradius", 0.0),
+                        focal_distance: params.float("focaldistance", 1e6),
+                        aspect_ratio: params
+                            .numbers("frameaspectratio")
+                            .and_then(|n| n.first().copied()),
+                    });
+                    params.finish(&context, &mut self.warnings);
+                }
+                "Film" => {
+                    let kind = tokens.text()?;
+                    let mut params = tokens.params()?;
+                    if kind != "image" {
+                        self.warnings
+                            .push(format!("{}: {} film isn't supported", context, kind));
+                    }
+                    let width = params.float("xresolution", 1280.0);
+                    let height = params.float("yresolution", 720.0);
+                    self.resolution = Some((width.max(1.0) as usize, height.max(1.0) as usize));
+                    params.take("filename"); //the caller decides where the image goes
+                    params.finish(&context, &mut self.warnings);
+                }
+                "Sampler" => {
+                    let _ = tokens.text()?; //every sampler becomes ours
+                    let mut params = tokens.params()?;
+                    self.samples = Some(params.float("pixelsamples", 16.0).max(1.0) as usize);
+                    params.finish(&context, &mut self.warnings);
+                }
+                "Integrator" => {
+                    let kind = tokens.text()?;
+                    let mut params = tokens.params()?;
+                    if kind != "path" && kind != "volpath" {
+                        self.warnings.push(format!(
+                            "{}: {} integration isn't supported; path tracing instead",
+                            context, kind
+                        ));
+                    }
+                    self.max_depth = Some(params.float("maxdepth", 5.0).max(0.0) as usize);
+                    params.finish(&context, &mut self.warnings);
+                }
+
+                "Material" => {
+                    let kind = tokens.text()?;
+                    let params = tokens.params()?;
+                    self.state.material = self.material(&kind, params, &context);
+                }
+                "MakeNamedMaterial" => {
+                    let name = tokens.text()?;
+                    let mut params = tokens.params()?;
+                    let kind = params.text("type").unwrap_or_default();
+                    let material = self.material(&kind, params, &context);
+                    self.named_materials.insert(name, material);
+                }
+                "NamedMaterial" => {
+                    let name = tokens.text()?;
+                    match self.named_materials.get(&name) {
+                        Some(material) => self.state.material = material.clone(),
+                        None => self
+                            .warnings
+                            .push(format!("{}: no material named {:?}", context, name)),
+                    }
+                }
+
+                "LightSource" => {
+                    let kind = tokens.text()?;
+                    let params = tokens.params()?;
+                    self.light(&kind, params, &context);
+                }
+                "AreaLightSource" => {
+                    let kind = tokens.text()?;
+                    let mut params = tokens.params()?;
+                    if kind != "diffuse" {
+                        self.warnings.push(format!(
+                            "{}: {} area lights aren't supported",
+                            context, kind
+                        ));
+                    }
+                    let radiance =
+                        params.colour("L", Colour::new(1.0, 1.0, 1.0)) * params.float("scale", 1.0);
+                    self.state.area_light = Some(radiance);
+                    params.finish(&context, &mut self.warnings);
+                }
+                "Shape" => {
+                    let kind = tokens.text()?;
+                    let params = tokens.params()?;
+                    if !self.in_object {
+                        self.shape(&kind, params, &base, &context, &tokens)?;
+                    }
+                }
+
+                "ObjectBegin" => {
+                    let name = tokens.text()?;
+                    self.warnings.push(format!(
+                        "{}: instancing isn't supported, so {:?} is left out",
+                        context, name
+                    ));
+                    self.attributes.push(self.state.clone());
+                    self.in_object = true;
+                }
+                "ObjectEnd" => {
+                    if let Some(state) = self.attributes.pop() {
+                        self.state = state;
+                    }
+                    self.in_object = false;
+                }
+                other => {
+                    tokens.skip_arguments();
+                    self.warnings
+                        .push(format!("{}: {} isn't supported", context, other));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn concatenate(&mut self, transform: Transform) {
+        self.state.transform = self.state.transform * transform;
+    }
+
+    //pbrt's world is left-handed; mirroring it lets our right-handed camera see the same picture
+    fn to_world(&self) -> Transform {
+        Transform::scale(Vec3::new(1.0, 1.0, -1.0)) * self.state.transform
+    }
+
+    fn material(&mut self, kind: &str, mut params: Params, context: &str) -> Material {
+        let material = match kind {
+            "matte" => {
+                if params.float("sigma", 0.0) != 0.0 {
+                    self.warnings
+                        .push(format!("{}: matte sigma isn't supported", context));
+                }
+                Material::Lambertian(params.colour("Kd", Colour::new(0.5, 0.5, 0.5)))
+            }
+            "metal" => {
+                let roughness = roughness(&mut params, 0.01);
+                let mut metal = Material::copper(roughness);
+                if let Material::Conductor(ref mut eta, ref mut k, _) = metal {
+                    *eta = params.colour("eta", *eta);
+                    *k = params.colour("k", *k);
+                }
+                metal
+            }
+            "mirror" => Material::Metal(params.colour("Kr", Colour::new(0.9, 0.9, 0.9)), 0.0),
+            "glass" => {
+                let white = Colour::new(1.0, 1.0, 1.0);
+                if params.colour("Kr", white) != white || params.colour("Kt", white) != white {
+                    self.warnings
+                        .push(format!("{}: tinted glass isn't supported", context));
+                }
+                let index = params.float("index", 1.5);
+                match roughness(&mut params, 0.0) {
+                    0.0 => Material::Dielectric(index),
+                    roughness => Material::RoughDielectric(index, roughness, white),
+                }
+            }
+            other => {
+                self.warnings.push(format!(
+                    "{}: {:?} materials aren't supported; using a matte one",
+                    context, other
+                ));
+                let diffuse = params.colour("Kd", Colour::new(0.5, 0.5, 0.5));
+                params.list.clear();
+                Material::Lambertian(diffuse)
+            }
+        };
+        params.finish(context, &mut self.warnings);
+        self.materials += 1;
+        material.with_id(self.materials - 1)
+    }
+
+    fn light(&mut self, kind: &str, mut params: Params, context: &str) {
+        let scale = params.colour("scale", Colour::new(1.0, 1.0, 1.0));
+        match kind {
+            "infinite" => {
+                if params.text("mapname").is_some() {
+                    self.warnings.push(format!(
+                        "{}: environment maps aren't supported; lighting evenly instead",
+                        context
+                    ));
+                }
+                params.take("samples");
+                self.background =
+                    self.background + scale * params.colour("L", Colour::new(1.0, 1.0, 1.0));
+            }
+            "point" | "spot" => {
+                let point = |params: &mut Params, name: &str, default: Point| match params
+                    .numbers(name)
+                    .as_deref()
+                {
+                    Some(&[x, y, z]) => Point::new(x, y, z),
+                    _ => default,
+                };
+                let from = point(&mut params, "from", Point::default());
+                let intensity = scale * params.colour("I", Colour::new(1.0, 1.0, 1.0));
+                let mut point_light = PointLight::new(self.to_world().point(from), intensity);
+                if kind == "spot" {
+                    let to = point(&mut params, "to", Point::new(0.0, 0.0, 1.0));
+                    let angle = params.float("coneangle", 30.0);
+                    let fade = params.float("conedelta", 5.0);
+                    point_light =
+                        point_light.with_cone(self.to_world().vector(to - from), angle, fade);
+                }
+                self.world.add_light(point_light);
+                self.warnings.push(format!(
+                    "{}: {} lights are too small to see in mirrors or perfectly smooth glass",
+                    context, kind
+                ));
+            }
+            other => self
+                .warnings
+                .push(format!("{}: {} lights aren't supported", context, other)),
+        }
+        params.finish(context, &mut self.warnings);
+    }
+
+    fn shape(
+        &mut self,
+        kind: &str,
+        mut params: Params,
+        base: &Path,
+        context: &str,
+        tokens: &Tokens,
+    ) -> Result<(), PbrtError> {
+        let material = match self.state.area_light {
+            Some(radiance) => Material::DiffuseLight(radiance),
+            None => self.state.material.clone(),
+        };
+        let transform = self.to_world();
+        match kind {
+            "sphere" => {
+                let radius = params.float("radius", 1.0);
+                //spheres stay round, so squashing them can only be approximated
+                let scales = [
+                    Vec3::new(1.0, 0.0, 0.0),
+                    Vec3::new(0.0, 1.0, 0.0),
+                    Vec3::new(0.0, 0.0, 1.0),
+                ]
+                .map(|axis| transform.vector(axis).length());
+                let (least, most) = (
+                    scales.iter().copied().fold(f64::INFINITY, f64::min),
+                    scales.iter().copied().fold(0.0, f64::max),
+                );
+                if most - least > 1e-6 * most {
+                    self.warnings.push(format!(
+                        "{}: a sphere is scaled unevenly, so it's left round",
+                        context
+                    ));
+                }
+                let scale = scales.iter().sum::<f64>() / 3.0;
+                self.world.add(Sphere::new(
+                    transform.point(Point::default()),
+                    radius * scale,
+                    material,
+                ));
+            }
+            "trianglemesh" => {
+                let positions: Vec<Point> = params
+                    .numbers("P")
+                    .unwrap_or_default()
+                    .chunks_exact(3)
+                    .map(|p| Point::new(p[0], p[1], p[2]))
+                    .collect();
+                let indices: Vec<usize> = match params.numbers("indices") {
+                    Some(indices) => indices
+                        .into_iter()
+                        .map(|index| match index >= 0.0 && index.fract() == 0.0 {
+                            true => Ok(index as usize),
+                            false => Err(tokens.error(format!(
+                                "trianglemesh index {} isn't a vertex index",
+                                index
+                            ))),
+                        })
+                        .collect::<Result<_, _>>()?,
+                    None if positions.len() == 3 => vec![0, 1, 2],
+                    None => return Err(tokens.error("a trianglemesh needs indices".to_string())),
+                };
+                if !indices.len().is_multiple_of(3) {
+                    return Err(
+                        tokens.error("trianglemesh indices don't make whole triangles".to_string())
+                    );
+                }
+                let mut indices: Vec<[usize; 3]> = indices
+                    .chunks_exact(3)
+                    .map(|t| [t[0], t[1], t[2]])
+                    .collect();
+                let normals: Option<Vec<Vec3>> = params.numbers("N").map(|n| {
+                    n.chunks_exact(3)
+                        .map(|n| Vec3::new(n[0], n[1], n[2]))
+                        .collect()
+                });
+                let uvs: Option<Vec<(f64, f64)>> = params
+                    .numbers("uv")
+                    .or_else(|| params.numbers("st"))
+                    .map(|uv| uv.chunks_exact(2).map(|uv| (uv[0], uv[1])).collect());
+
+                if let Some(&index) = indices.iter().flatten().find(|&&i| i >= positions.len()) {
+                    return Err(MeshError::IndexOutOfRange(index, positions.len()).into());
+                }
+                let normals = normals.filter(|normals| normals.len() == positions.len());
+                if let Some(normals) = &normals {
+                    //pbrt faces triangles the way their normals point, whatever their winding
+                    for triangle in &mut indices {
+                        let [a, b, c] = triangle.map(|i| positions[i]);
+                        let shading =
+                            normals[triangle[0]] + normals[triangle[1]] + normals[triangle[2]];
+                        if Vec3::dot(Vec3::cross(b - a, c - a), shading) < 0.0 {
+                            triangle.swap(1, 2);
+                        }
+                    }
+                }
+                let count = positions.len();
+                let mut mesh = TriangleMesh::new(positions, indices, material);
+                if let Some(normals) = normals {
+                    mesh = mesh.with_normals(normals);
+                }
+                if let Some(uvs) = uvs.filter(|uvs| uvs.len() == count) {
+                    mesh = mesh.with_uvs(uvs);
+                }
+                self.world.add(mesh.transformed(&transform));
+            }
+            "plymesh" => {
+                let file = params.text("filename").unwrap_or_default();
+                let mesh = TriangleMesh::from_ply(base.join(file), material)?;
+                self.world.add(mesh.transformed(&transform));
+            }
+            other => {
+                self.warnings
+                    .push(format!("{}: {} shapes aren't supported", context, other));
+                params.list.clear();
+            }
+        }
+        params.finish(context, &mut self.warnings);
+        Ok(())
+    }
+
+    fn finish(mut self) -> PbrtScene {
+        //pbrt has no sky, so only infinite lights light up the background
+        self.world.set_background(self.background);
+
+        let (width, height) = self.resolution.unwrap_or((1280, 720));
+        let settings = self.camera.take().unwrap_or(CameraSettings {
+            camera_from_world: Transform::identity(),
+            projection: Projection::Perspective,
+            fov: 90.0,
+            lens_radius: 0.0,
+            focal_distance: 1e6,
+            aspect_ratio: None,
+        });
+        let aspect_ratio = settings
+            .aspect_ratio
+            .unwrap_or(width as f64 / height as f64);
+        //the field of view spans the shorter side of the image
+        let vfov = match aspect_ratio >= 1.0 {
+            true => settings.fov,
+            false => {
+                2.0 * ((0.5 * settings.fov).to_radians().tan() / aspect_ratio)
+                    .atan()
+                    .to_degrees()
+            }
+        };
+        let world_from_camera = settings.camera_from_world.inverse().unwrap_or_else(|| {
+            self.warnings.push(
+                "the camera's transformation can't be inverted; using the default view".to_string(),
+            );
+            Transform::identity()
+        });
+        //pinholes focus everywhere, and pbrt's default focal distance of a million would push hits below t_min
+        let focus_dist = match settings.lens_radius > 0.0 {
+            true => settings.focal_distance,
+            false => 1.0,
+        };
+        let camera_to_world = Transform::scale(Vec3::new(1.0, 1.0, -1.0)) * world_from_camera;
+        let origin = camera_to_world.point(Point::default());
+        let camera = Camera::new(
+            origin,
+            origin + camera_to_world.vector(Vec3::new(0.0, 0.0, 1.0)),
+            camera_to_world.vector(Vec3::new(0.0, 1.0, 0.0)),
+            vfov,
+            width as f64 / height as f64,
+            2.0 * settings.lens_radius,
+            focus_dist,
+        )
+        .with_projection(match settings.projection {
+            //pbrt's screen window spans -1 to 1 along the shorter side of the image
+            Projection::Orthographic(_) if aspect_ratio < 1.0 => {
+                Projection::Orthographic(2.0 / aspect_ratio)
+            }
+            projection => projection,
+        });
+
+        let viewport = Viewport::new(
+            width,
+            height,
+            self.samples.unwrap_or(16),
+            self.max_depth.unwrap_or(5) + 1,
+        );
+        PbrtScene {
+            world: self.world,
+            camera,
+            viewport,
+            warnings: self.warnings,
+        }
+    }
+}
+
+//pbrt's roughness parameters, remapped from its perceptual scale to ours through GGX alpha
+fn roughness(params: &mut Params, default: f64) -> f64 {
+    let u = params.float("uroughness", default);
+    let v = params.float("vroughness", default);
+    let roughness = params.float("roughness", 0.5 * (u + v));
+    let alpha = match params.bool("remaproughness", true) {
+        true if roughness > 0.0 => {
+            let x = roughness.max(1e-3).ln();
+            1.62142
+                + 0.819955 * x
+                + 0.1734 * x * x
+                + 0.0171201 * x.powi(3)
+                + 0.000640711 * x.powi(4)
+        }
+        _ => roughness,
+    };
+    alpha.sqrt()
+}
+
+//pbrt lists matrices a column at a time
+fn columns(n: &[f64]) -> Transform {
+    Transform::from_columns([
+        [n[0], n[1], n[2], n[3]],
+        [n[4], n[5], n[6], n[7]],
+        [n[8], n[9], n[10], n[11]],
+        [n[12], n[13], n[14], n[15]],
+    ])
+}
+
+//from world to camera space, where the camera looks along +z with +y up
+fn look_at(eye: Point, target: Point, up: Vec3) -> Option<Transform> {
+    let direction = (target - eye).unit();
+    let right = Vec3::cross(up.unit(), direction);
+    if right.is_near_zero() {
+        return None;
+    }
+    let right = right.unit();
+    let up = Vec3::cross(direction, right);
+    let e = |v: Vec3, w: f64| [v.e[0], v.e[1], v.e[2], w];
+    Transform::from_columns([e(right, 0.0), e(up, 0.0), e(direction, 0.0), e(eye, 1.0)]).inverse()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray::{Hittable, Ray};
+
+    //writes a scene to a temporary file and imports it
+    fn load(name: &str, text: &str) -> (PathBuf, Result<PbrtScene, PbrtError>) {
+        let path = std::env::temp_dir().join(name);
+        fs::write(&path, text).unwrap();
+        let scene = load_pbrt(&path);
+        fs::remove_file(&path).unwrap();
+        (path, scene)
+    }
+
+    fn triangle(indices: &str) -> String {
+        format!(
+            "WorldBegin\n\nShape \"trianglemesh\"\n  \"point P\" [0 0 0 1 0 0 0 1 0]\n  \"integer indices\" [{}]\nWorldEnd\n",
+            indices
+        )
+    }
+
+    #[test]
+    fn bad_triangle_indices_are_rejected_where_they_are() {
+        for (name, indices) in [
+            ("raytracer_negative_index.pbrt", "0 1 -1"),
+            ("raytracer_fractional_index.pbrt", "0 1.5 2"),
+            ("raytracer_partial_triangle.pbrt", "0 1"),
+        ] {
+            match load(name, &triangle(indices)) {
+                (path, Err(PbrtError::Parse(file, line, _))) => {
+                    assert_eq!(file, path);
+                    assert_eq!(line, 5);
+                }
+                (_, Err(other)) => panic!("{}: expected a parse error, not {}", indices, other),
+                (_, Ok(_)) => panic!("{}: expected a parse error", indices),
+            }
+        }
+    }
+
+    #[test]
+    fn point_and_spot_lights_are_warned_about() {
+        let text = "LightSource \"point\" \"rgb I\" [1 1 1]\n\
+                    LightSource \"spot\" \"point from\" [0 1 0] \"point to\" [0 0 0]\n\
+                    WorldBegin\nWorldEnd\n";
+        let (_, scene) = load("raytracer_point_lights.pbrt", text);
+        let scene = scene.unwrap();
+        assert_eq!(
+            scene
+                .warnings
+                .iter()
+                .filter(|warning| warning.contains("lights are too small"))
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn materials_are_numbered_in_the_order_they_are_declared() {
+        let text = "WorldBegin\n\
+                    MakeNamedMaterial \"shiny\" \"string type\" \"metal\"\n\
+                    Material \"matte\"\n\
+                    AttributeBegin\nTranslate -3 0 0\nShape \"sphere\"\nAttributeEnd\n\
+                    NamedMaterial \"shiny\"\n\
+                    Shape \"sphere\"\n\
+                    AttributeBegin\nTranslate 3 0 0\nShape \"sphere\"\nAttributeEnd\n\
+                    WorldEnd\n";
+        let (_, scene) = load("raytracer_material_ids.pbrt", text);
+        let scene = scene.unwrap();
+        let id = |x: f64| {
+            let ray = Ray::new(Point::new(x, 0.0, 10.0), Vec3::new(0.0, 0.0, -1.0));
+            let rec = scene.world.hit(ray, 0.001, f64::INFINITY).unwrap();
+            rec.material.id()
+        };
+        //the named material was declared first, and both spheres using it share its number
+        assert_eq!(id(-3.0), Some(1));
+        assert_eq!(id(0.0), Some(0));
+        assert_eq!(id(3.0), Some(0));
+    }
+}
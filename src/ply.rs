@@ -0,0 +1,414 @@
+//the Stanford polygon format: an ascii header describing elements and their properties, then the data
+//for every element in order, as whitespace-separated text or packed binary
+
+use crate::{
+    material::{Colour, Material},
+    mesh::{MeshError, TriangleMesh},
+    texture::srgb_to_linear,
+    Point, Vec3,
+};
+
+#[derive(Clone, Copy)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+impl Scalar {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "char" | "int8" => Self::I8,
+            "uchar" | "uint8" => Self::U8,
+            "short" | "int16" => Self::I16,
+            "ushort" | "uint16" => Self::U16,
+            "int" | "int32" => Self::I32,
+            "uint" | "uint32" => Self::U32,
+            "float" | "float32" => Self::F32,
+            "double" | "float64" => Self::F64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Self::I8 | Self::U8 => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::I32 | Self::U32 | Self::F32 => 4,
+            Self::F64 => 8,
+        }
+    }
+
+    //whether 8-bit colours need scaling down to [0, 1]
+    fn is_integer(self) -> bool {
+        !matches!(self, Self::F32 | Self::F64)
+    }
+}
+
+enum Property {
+    Scalar(String, Scalar),
+    List(String, Scalar, Scalar), //a length of the first type, followed by that many of the second
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+//where the values of the body come from, one at a time
+enum Values<'a> {
+    Ascii(Vec<(usize, &'a str)>, usize), //each token with its line number, and the next token
+    Binary(&'a [u8], usize, bool),       //the data, the next byte, and whether it's big-endian
+}
+impl Values<'_> {
+    //the line the last value came from, or 0 in binary data
+    fn line(&self) -> usize {
+        match self {
+            Self::Ascii(tokens, next) => next.checked_sub(1).map_or(0, |last| tokens[last].0),
+            Self::Binary(..) => 0,
+        }
+    }
+
+    fn next(&mut self, scalar: Scalar) -> Result<f64, MeshError> {
+        match self {
+            Self::Ascii(tokens, next) => {
+                let &(line, token) = tokens.get(*next).ok_or(MeshError::Truncated)?;
+                *next += 1;
+                token.parse().map_err(|_| {
+                    MeshError::Parse(line, format!("expected a number, not {:?}", token))
+                })
+            }
+            Self::Binary(data, next, big_endian) => {
+                let size = scalar.size();
+                let bytes = data.get(*next..*next + size).ok_or(MeshError::Truncated)?;
+                *next += size;
+                let mut buffer = [0; 8];
+                buffer[..size].copy_from_slice(bytes);
+                if *big_endian {
+                    buffer[..size].reverse();
+                }
+                let [b0, b1, b2, b3, ..] = buffer;
+                Ok(match scalar {
+                    Scalar::I8 => b0 as i8 as f64,
+                    Scalar::U8 => b0 as f64,
+                    Scalar::I16 => i16::from_le_bytes([b0, b1]) as f64,
+                    Scalar::U16 => u16::from_le_bytes([b0, b1]) as f64,
+                    Scalar::I32 => i32::from_le_bytes([b0, b1, b2, b3]) as f64,
+                    Scalar::U32 => u32::from_le_bytes([b0, b1, b2, b3]) as f64,
+                    Scalar::F32 => f32::from_le_bytes([b0, b1, b2, b3]) as f64,
+                    Scalar::F64 => f64::from_le_bytes(buffer),
+                })
+            }
+        }
+    }
+}
+
+pub(crate) fn parse(bytes: &[u8], material: Material) -> Result<TriangleMesh, MeshError> {
+    //end_header starts its own line; comments may mention it anywhere else
+    let header_end = find(bytes, b"\nend_header")
+        .map(|i| i + 1)
+        .ok_or_else(|| MeshError::Header("no end_header line".to_string()))?;
+    let header = std::str::from_utf8(&bytes[..header_end])
+        .map_err(|_| MeshError::Header("the header isn't text".to_string()))?;
+    //the body starts on the line after end_header
+    let body_start = bytes[header_end..]
+        .iter()
+        .position(|&b| b == b'\n')
+        .map_or(bytes.len(), |i| header_end + i + 1);
+    let body = &bytes[body_start..];
+
+    let mut lines = header.lines();
+    if lines.next().map(str::trim) != Some("ply") {
+        return Err(MeshError::Header("not a PLY file".to_string()));
+    }
+    let mut format = None;
+    let mut elements: Vec<Element> = vec![];
+    for line in lines {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words[..] {
+            [] | ["comment", ..] | ["obj_info", ..] => {}
+            ["format", name, _] => format = Some(name),
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| MeshError::Header(format!("bad element count in {:?}", line)))?,
+                properties: vec![],
+            }),
+            ["property", "list", count, item, name] => {
+                let property = match (Scalar::parse(count), Scalar::parse(item)) {
+                    (Some(count), Some(item)) => Property::List(name.to_string(), count, item),
+                    _ => return Err(MeshError::Header(format!("unknown type in {:?}", line))),
+                };
+                push_property(&mut elements, property, line)?;
+            }
+            ["property", scalar, name] => {
+                let scalar = Scalar::parse(scalar)
+                    .ok_or_else(|| MeshError::Header(format!("unknown type in {:?}", line)))?;
+                push_property(
+                    &mut elements,
+                    Property::Scalar(name.to_string(), scalar),
+                    line,
+                )?;
+            }
+            _ => return Err(MeshError::Header(format!("unrecognised line {:?}", line))),
+        }
+    }
+
+    let mut values = match format {
+        Some("ascii") => {
+            let text = std::str::from_utf8(body).map_err(|_| {
+                MeshError::Parse(0, "the body of an ascii file isn't text".to_string())
+            })?;
+            //after the header's lines and the end_header line
+            let first_line = header.lines().count() + 2;
+            let tokens = text
+                .lines()
+                .enumerate()
+                .flat_map(|(i, line)| {
+                    line.split_whitespace()
+                        .map(move |token| (first_line + i, token))
+                })
+                .collect();
+            Values::Ascii(tokens, 0)
+        }
+        Some("binary_little_endian") => Values::Binary(body, 0, false),
+        Some("binary_big_endian") => Values::Binary(body, 0, true),
+        Some(other) => return Err(MeshError::Header(format!("unsupported format {:?}", other))),
+        None => return Err(MeshError::Header("no format line".to_string())),
+    };
+
+    let mut positions = vec![];
+    let mut normals = vec![];
+    let mut colours = vec![];
+    let mut uvs = vec![];
+    let mut indices = vec![];
+    for element in &elements {
+        for _ in 0..element.count {
+            //every property must be read to get past it, wanted or not
+            let mut scalars = vec![];
+            let mut lists = vec![];
+            for property in &element.properties {
+                match *property {
+                    Property::Scalar(ref name, scalar) => {
+                        scalars.push((name.as_str(), scalar, values.next(scalar)?))
+                    }
+                    Property::List(ref name, count, item) => {
+                        let count = values.next(count)? as usize;
+                        let list = (0..count)
+                            .map(|_| values.next(item))
+                            .collect::<Result<Vec<f64>, _>>()?;
+                        lists.push((name.as_str(), list));
+                    }
+                }
+            }
+            let scalar = |names: &[&str]| {
+                scalars
+                    .iter()
+                    .find(|(name, _, _)| names.contains(name))
+                    .map(|&(_, scalar, value)| (scalar, value))
+            };
+
+            match element.name.as_str() {
+                "vertex" => {
+                    match (scalar(&["x"]), scalar(&["y"]), scalar(&["z"])) {
+                        (Some(x), Some(y), Some(z)) => positions.push(Point::new(x.1, y.1, z.1)),
+                        _ => {
+                            return Err(MeshError::Header("vertices have no position".to_string()))
+                        }
+                    }
+                    if let (Some(x), Some(y), Some(z)) =
+                        (scalar(&["nx"]), scalar(&["ny"]), scalar(&["nz"]))
+                    {
+                        normals.push(Vec3::new(x.1, y.1, z.1));
+                    }
+                    let channels = [
+                        scalar(&["red", "r", "diffuse_red"]),
+                        scalar(&["green", "g", "diffuse_green"]),
+                        scalar(&["blue", "b", "diffuse_blue"]),
+                    ];
+                    if let [Some(r), Some(g), Some(b)] = channels {
+                        //8-bit colours are sRGB-encoded, like most images
+                        let decode = |(scalar, value): (Scalar, f64)| match scalar.is_integer() {
+                            true => srgb_to_linear(value / 255.0),
+                            false => value,
+                        };
+                        colours.push(Colour::new(decode(r), decode(g), decode(b)));
+                    }
+                    let u = scalar(&["u", "s", "texture_u", "texture_s"]);
+                    let v = scalar(&["v", "t", "texture_v", "texture_t"]);
+                    if let (Some(u), Some(v)) = (u, v) {
+                        uvs.push((u.1, v.1));
+                    }
+                }
+                "face" => {
+                    let (_, list) = lists
+                        .iter()
+                        .find(|(name, _)| *name == "vertex_indices" || *name == "vertex_index")
+                        .ok_or_else(|| {
+                            MeshError::Header("faces have no vertex indices".to_string())
+                        })?;
+                    let list = list
+                        .iter()
+                        .map(|&index| match index >= 0.0 && index.fract() == 0.0 {
+                            true => Ok(index as usize),
+                            false => Err(MeshError::Parse(
+                                values.line(),
+                                format!("{} isn't a vertex index", index),
+                            )),
+                        })
+                        .collect::<Result<Vec<usize>, _>>()?;
+                    //polygons become fans of triangles
+                    for i in 1..list.len().saturating_sub(1) {
+                        indices.push([list[0], list[i], list[i + 1]]);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let count = positions.len();
+    let mut mesh = TriangleMesh::checked(positions, indices, material)?;
+    if !normals.is_empty() && normals.len() == count {
+        mesh = mesh.with_normals(normals);
+    }
+    if !colours.is_empty() && colours.len() == count {
+        mesh = mesh.with_colours(colours);
+    }
+    if !uvs.is_empty() && uvs.len() == count {
+        mesh = mesh.with_uvs(uvs);
+    }
+    Ok(mesh)
+}
+
+fn push_property(
+    elements: &mut [Element],
+    property: Property,
+    line: &str,
+) -> Result<(), MeshError> {
+    elements
+        .last_mut()
+        .ok_or_else(|| MeshError::Header(format!("property before any element: {:?}", line)))?
+        .properties
+        .push(property);
+    Ok(())
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray::{Hittable, Ray};
+
+    fn fixture(name: &str) -> Vec<u8> {
+        let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name);
+        std::fs::read(path).unwrap()
+    }
+
+    //the fixtures are a unit square in the xz plane, facing up, in one orange
+    fn assert_square(mesh: &TriangleMesh) {
+        let bounds = mesh.bounding_box().unwrap();
+        assert_eq!(bounds.min.e, [0.0, 0.0, 0.0]);
+        assert_eq!(bounds.max.e, [1.0, 0.0, 1.0]);
+        let orange = Colour::new(1.0, srgb_to_linear(128.0 / 255.0), 0.0);
+        //one ray down through each of the two triangles the quad becomes
+        for (x, z) in [(0.2, 0.7), (0.7, 0.2)] {
+            let down = Ray::new(Point::new(x, 1.0, z), Vec3::new(0.0, -1.0, 0.0));
+            let rec = mesh.hit(down, 0.0001, f64::INFINITY).unwrap();
+            assert!((rec.t - 1.0).abs() < 1e-9);
+            assert!(rec.front_face);
+            let colour = rec.vertex_colour.unwrap();
+            assert!((colour - orange).length() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn ascii() {
+        assert_square(&parse(&fixture("square_ascii.ply"), Material::default()).unwrap());
+    }
+
+    #[test]
+    fn binary_little_endian() {
+        assert_square(&parse(&fixture("square_binary_le.ply"), Material::default()).unwrap());
+    }
+
+    #[test]
+    fn binary_big_endian() {
+        assert_square(&parse(&fixture("square_binary_be.ply"), Material::default()).unwrap());
+    }
+
+    const HEADER: &str = "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\n\
+                          property float z\nelement face 1\nproperty list uchar int vertex_indices\nend_header\n";
+
+    fn parse_ascii(body: &str) -> Result<TriangleMesh, MeshError> {
+        parse(
+            format!("{}{}", HEADER, body).as_bytes(),
+            Material::default(),
+        )
+    }
+
+    #[test]
+    fn truncated() {
+        let error = parse_ascii("0 0 0\n1 0 0\n0 0 1\n3 0 1").err().unwrap();
+        assert!(matches!(error, MeshError::Truncated));
+        let binary = fixture("square_binary_le.ply");
+        let error = parse(&binary[..binary.len() - 4], Material::default())
+            .err()
+            .unwrap();
+        assert!(matches!(error, MeshError::Truncated));
+    }
+
+    #[test]
+    fn index_out_of_range() {
+        let error = parse_ascii("0 0 0\n1 0 0\n0 0 1\n3 0 1 7\n").err().unwrap();
+        assert!(matches!(error, MeshError::IndexOutOfRange(7, 3)));
+        //negative and fractional indices can't be vertices either
+        let error = parse_ascii("0 0 0\n1 0 0\n0 0 1\n3 0 1 -1\n")
+            .err()
+            .unwrap();
+        assert!(matches!(error, MeshError::Parse(13, _)));
+        let error = parse_ascii("0 0 0\n1 0 0\n0 0 1\n3 0 1 1.5\n")
+            .err()
+            .unwrap();
+        assert!(matches!(error, MeshError::Parse(13, _)));
+    }
+
+    #[test]
+    fn not_a_number() {
+        let error = parse_ascii("0 0 0\n1 zero 0\n0 0 1\n3 0 1 2\n")
+            .err()
+            .unwrap();
+        assert!(matches!(error, MeshError::Parse(11, _)));
+    }
+
+    #[test]
+    fn bad_header() {
+        for header in [
+            "obj\nend_header\n",
+            "ply\nend_header\n",
+            "ply\nformat ascii 1.0\nelement vertex many\nend_header\n",
+            "ply\nformat ascii 1.0\nproperty float x\nend_header\n",
+            "ply\nformat ascii 1.0\nelement vertex 1\nproperty quaternion x\nend_header\n",
+            "ply\nformat binary_middle_endian 1.0\nend_header\n",
+            "ply\nformat ascii 1.0\ncomment no end_header here\n",
+        ] {
+            let error = parse(header.as_bytes(), Material::default()).err();
+            assert!(
+                matches!(error, Some(MeshError::Header(_))),
+                "{:?} should be rejected",
+                header
+            );
+        }
+    }
+}
@@ -0,0 +1,377 @@
+use std::f64::consts::PI;
+
+use rand::Rng;
+
+use crate::{
+    lpe::Event,
+    material::Colour,
+    microfacet::{self, Frame},
+    ray::{HitRecord, Ray},
+    texture::Texture,
+    Vec3,
+};
+
+//Disney's principled BSDF (Burley, 2012 and 2015): a single material whose parameters span plastics,
+//metals, fabrics, lacquered surfaces and glass. Parameters other than the colours are in [0, 1].
+#[derive(Clone)]
+pub struct Principled {
+    pub base_colour: Texture,
+    pub metallic: f64,
+    pub roughness: f64,
+    //strength of the dielectric highlight; 0.5 is about 4% reflectance
+    pub specular: f64,
+    //tints the dielectric highlight towards the base colour
+    pub specular_tint: f64,
+    //grazing retroreflection, for cloth
+    pub sheen: f64,
+    //a second, colourless glossy layer
+    pub clearcoat: f64,
+    pub clearcoat_gloss: f64,
+    //how much of the dielectric part is glass rather than diffuse
+    pub transmission: f64,
+    pub ior: f64,
+    //scales roughness by the green channel and metallic by the blue one, as glTF packs them
+    pub metallic_roughness: Option<Texture>,
+}
+impl Default for Principled {
+    fn default() -> Self {
+        Self {
+            base_colour: Texture::Solid(Colour::new(0.8, 0.8, 0.8)),
+            metallic: 0.0,
+            roughness: 0.5,
+            specular: 0.5,
+            specular_tint: 0.0,
+            sheen: 0.0,
+            clearcoat: 0.0,
+            clearcoat_gloss: 1.0,
+            transmission: 0.0,
+            ior: 1.5,
+            metallic_roughness: None,
+        }
+    }
+}
+impl Principled {
+    //the BSDF with its textures looked up at a hit
+    pub fn bsdf(&self, rec: &HitRecord) -> PrincipledBsdf {
+        let mut metallic = self.metallic;
+        let mut roughness = self.roughness;
+        if let Some(texture) = &self.metallic_roughness {
+            let packed = texture.value(rec);
+            roughness *= packed.e[1];
+            metallic *= packed.e[2];
+        }
+
+        PrincipledBsdf {
+            frame: Frame::new(rec.normal),
+            base_colour: self.base_colour.value(rec),
+            metallic,
+            roughness,
+            specular: self.specular,
+            specular_tint: self.specular_tint,
+            sheen: self.sheen,
+            clearcoat: self.clearcoat,
+            clearcoat_gloss: self.clearcoat_gloss,
+            transmission: self.transmission,
+            eta: if rec.front_face {
+                self.ior.recip()
+            } else {
+                self.ior
+            },
+        }
+    }
+
+    //the event is the lobe that chose the direction, and the weight is only that lobe's share of the BSDF
+    pub fn scatter(&self, r_in: Ray, rec: &HitRecord) -> Option<(Colour, Ray, Event)> {
+        let bsdf = self.bsdf(rec);
+        let wo = -r_in.direction.unit();
+        let (wi, event) = bsdf.sample_event(wo)?;
+
+        let (lobe, pdf) = bsdf.lobe(wo, wi, event);
+        if pdf <= 0.0 {
+            return None;
+        }
+        let cosine = Vec3::dot(wi, rec.normal).abs();
+        Some((lobe * cosine / pdf, Ray::new(rec.p, wi), event))
+    }
+
+    //each lobe's share of the light arriving from wi that leaves along the ray, and the chance density of
+    //`scatter` picking wi
+    pub(crate) fn evaluate(&self, r_in: Ray, rec: &HitRecord, wi: Vec3) -> ([Colour; 3], f64) {
+        let bsdf = self.bsdf(rec);
+        let wo = -r_in.direction.unit();
+        let cosine = Vec3::dot(wi, rec.normal).abs();
+        (
+            bsdf.eval_lobes(wo, wi).map(|lobe| lobe * cosine),
+            bsdf.pdf(wo, wi),
+        )
+    }
+}
+
+//the principled BSDF at a single point. Directions are world space and point away from the surface:
+//wo towards the viewer, wi towards the light
+#[derive(Clone, Copy)]
+pub struct PrincipledBsdf {
+    frame: Frame,
+    base_colour: Colour,
+    metallic: f64,
+    roughness: f64,
+    specular: f64,
+    specular_tint: f64,
+    sheen: f64,
+    clearcoat: f64,
+    clearcoat_gloss: f64,
+    transmission: f64,
+    eta: f64, //incident over transmitted index of refraction
+}
+impl PrincipledBsdf {
+    pub fn eval(&self, wo: Vec3, wi: Vec3) -> Colour {
+        let [diffuse, specular, transmission] = self.eval_lobes(wo, wi);
+        diffuse + specular + transmission
+    }
+
+    //`eval` split into the diffuse and sheen, the glossy reflections, and the transmission
+    pub fn eval_lobes(&self, wo: Vec3, wi: Vec3) -> [Colour; 3] {
+        let (wo, wi) = (self.frame.to_local(wo), self.frame.to_local(wi));
+        let none = Colour::default();
+        if wo.e[2] <= 0.0 {
+            return [none; 3];
+        }
+
+        if wi.e[2] > 0.0 {
+            let h = (wo + wi).unit();
+            let cos_d = Vec3::dot(wi, h);
+            let dielectric = 1.0 - self.metallic;
+
+            //diffuse with retroreflection at grazing angles for rough surfaces
+            let fd90 = 0.5 + 2.0 * self.roughness * cos_d * cos_d;
+            let diffuse = self.base_colour / PI
+                * (1.0 + (fd90 - 1.0) * schlick_weight(wi.e[2]))
+                * (1.0 + (fd90 - 1.0) * schlick_weight(wo.e[2]))
+                * dielectric
+                * (1.0 - self.transmission);
+
+            let sheen = self.sheen * dielectric * schlick_weight(cos_d) * self.sheen_colour();
+
+            let alpha = microfacet::alpha(self.roughness);
+            let cspec0 = self.specular_colour();
+            let fresnel = cspec0 + (Colour::new(1.0, 1.0, 1.0) - cspec0) * schlick_weight(cos_d);
+            let specular = fresnel * microfacet::d(h, alpha) * microfacet::g2(wo, wi, alpha)
+                / (4.0 * wo.e[2] * wi.e[2]);
+
+            let clearcoat = 0.25
+                * self.clearcoat
+                * gtr1(h.e[2], self.clearcoat_alpha())
+                * (0.04 + 0.96 * schlick_weight(cos_d))
+                * microfacet::g1(wo, 0.25)
+                * microfacet::g1(wi, 0.25)
+                / (4.0 * wo.e[2] * wi.e[2]);
+
+            [
+                diffuse + sheen,
+                specular + Colour::new(clearcoat, clearcoat, clearcoat),
+                none,
+            ]
+        } else if wi.e[2] < 0.0 {
+            //rough dielectric transmission, after Walter et al.
+            let h = match self.refraction_half_vector(wo, wi) {
+                Some(h) => h,
+                None => return [none; 3],
+            };
+            let alpha = microfacet::alpha(self.roughness);
+            let (cos_o, cos_i) = (Vec3::dot(wo, h), Vec3::dot(wi, h));
+            let denominator = self.eta * cos_o + cos_i;
+            let transmitted = (1.0 - microfacet::fresnel_dielectric(cos_o, self.eta))
+                * microfacet::d(h, alpha)
+                * microfacet::g2(wo, wi, alpha)
+                * (cos_i * cos_o).abs()
+                / (wo.e[2] * wi.e[2].abs() * denominator * denominator);
+
+            //the base colour is reached after entering and leaving, so each crossing takes its root
+            let tint = Colour::new(
+                self.base_colour.e[0].sqrt(),
+                self.base_colour.e[1].sqrt(),
+                self.base_colour.e[2].sqrt(),
+            );
+            [
+                none,
+                none,
+                (1.0 - self.metallic) * self.transmission * transmitted * tint,
+            ]
+        } else {
+            [none; 3]
+        }
+    }
+
+    pub fn pdf(&self, wo: Vec3, wi: Vec3) -> f64 {
+        self.lobe_pdfs(wo, wi).iter().sum()
+    }
+
+    //`pdf` split into the chances of the diffuse, specular, clearcoat and transmission lobes picking wi
+    fn lobe_pdfs(&self, wo: Vec3, wi: Vec3) -> [f64; 4] {
+        let (wo, wi) = (self.frame.to_local(wo), self.frame.to_local(wi));
+        if wo.e[2] <= 0.0 {
+            return [0.0; 4];
+        }
+        let [diffuse, specular, clearcoat, transmission] = self.lobe_probabilities();
+        let alpha = microfacet::alpha(self.roughness);
+
+        if wi.e[2] > 0.0 {
+            let h = (wo + wi).unit();
+            [
+                diffuse * wi.e[2] / PI,
+                specular * microfacet::g1(wo, alpha) * microfacet::d(h, alpha) / (4.0 * wo.e[2]),
+                clearcoat * gtr1(h.e[2], self.clearcoat_alpha()) * h.e[2]
+                    / (4.0 * Vec3::dot(wo, h)),
+                0.0,
+            ]
+        } else {
+            let h = match self.refraction_half_vector(wo, wi) {
+                Some(h) => h,
+                None => return [0.0; 4],
+            };
+            let (cos_o, cos_i) = (Vec3::dot(wo, h), Vec3::dot(wi, h));
+            let denominator = self.eta * cos_o + cos_i;
+            let visible_normal_pdf =
+                microfacet::g1(wo, alpha) * cos_o * microfacet::d(h, alpha) / wo.e[2];
+            [
+                0.0,
+                0.0,
+                0.0,
+                transmission * visible_normal_pdf * cos_i.abs() / (denominator * denominator),
+            ]
+        }
+    }
+
+    //the part of `eval` that the lobes behind `event` sample, with the chance of one of them picking wi
+    fn lobe(&self, wo: Vec3, wi: Vec3, event: Event) -> (Colour, f64) {
+        let [diffuse, glossy, transmission] = self.eval_lobes(wo, wi);
+        let [diffuse_pdf, specular_pdf, clearcoat_pdf, transmission_pdf] = self.lobe_pdfs(wo, wi);
+        match event {
+            Event::Diffuse => (diffuse, diffuse_pdf),
+            //the specular and clearcoat lobes both pick glossy reflections
+            Event::Specular => (glossy, specular_pdf + clearcoat_pdf),
+            _ => (transmission, transmission_pdf),
+        }
+    }
+
+    pub fn sample(&self, wo: Vec3) -> Option<Vec3> {
+        Some(self.sample_event(wo)?.0)
+    }
+
+    //a direction as `sample` gives, with the kind of lobe it came from
+    pub fn sample_event(&self, wo: Vec3) -> Option<(Vec3, Event)> {
+        let wo = self.frame.to_local(wo);
+        if wo.e[2] <= 0.0 {
+            return None;
+        }
+        let [diffuse, specular, clearcoat, _] = self.lobe_probabilities();
+        let alpha = microfacet::alpha(self.roughness);
+        let mut rng = rand::thread_rng();
+        let lobe: f64 = rng.gen();
+
+        let (wi, event) = if lobe < diffuse {
+            let cosine_weighted = Vec3::new(0.0, 0.0, 1.0) + Vec3::random_unit();
+            let wi = match cosine_weighted.is_near_zero() {
+                true => Vec3::new(0.0, 0.0, 1.0),
+                false => cosine_weighted.unit(),
+            };
+            (wi, Event::Diffuse)
+        } else if lobe < diffuse + specular {
+            let wi = (-wo).reflect(microfacet::sample_visible_normal(wo, alpha));
+            (wi, Event::Specular)
+        } else if lobe < diffuse + specular + clearcoat {
+            let a2 = self.clearcoat_alpha().powi(2);
+            let cos_theta = ((1.0 - a2.powf(1.0 - rng.gen::<f64>())) / (1.0 - a2)).sqrt();
+            let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+            let phi = 2.0 * PI * rng.gen::<f64>();
+            let h = Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta);
+            ((-wo).reflect(h), Event::Specular)
+        } else {
+            let h = microfacet::sample_visible_normal(wo, alpha);
+            let cos_o = Vec3::dot(wo, h);
+            let sin2_t = self.eta * self.eta * (1.0 - cos_o * cos_o);
+            if sin2_t >= 1.0 {
+                return None; //total internal reflection; the specular lobe covers reflections
+            }
+            let wi = (self.eta * cos_o - (1.0 - sin2_t).sqrt()) * h - self.eta * wo;
+            (wi, Event::Transmission)
+        };
+
+        if wi.e[2] == 0.0 {
+            return None;
+        }
+        Some((self.frame.to_world(wi), event))
+    }
+
+    //chance of sampling each of the diffuse, specular, clearcoat and transmission lobes
+    fn lobe_probabilities(&self) -> [f64; 4] {
+        let dielectric = 1.0 - self.metallic;
+        let weights = [
+            dielectric * (1.0 - self.transmission),
+            1.0,
+            0.25 * self.clearcoat,
+            dielectric * self.transmission,
+        ];
+        let total: f64 = weights.iter().sum();
+        weights.map(|weight| weight / total)
+    }
+
+    //the microfacet normal that refracts wo into wi, if wi is a valid refraction of wo
+    fn refraction_half_vector(&self, wo: Vec3, wi: Vec3) -> Option<Vec3> {
+        let h = -(self.eta * wo + wi);
+        if h.is_near_zero() {
+            return None;
+        }
+        let h = if h.e[2] < 0.0 { -h.unit() } else { h.unit() };
+        if Vec3::dot(wo, h) <= 0.0 || Vec3::dot(wi, h) >= 0.0 {
+            return None;
+        }
+        Some(h)
+    }
+
+    //hue and saturation of the base colour, without its brightness
+    fn tint(&self) -> Colour {
+        let luminance = luminance(self.base_colour);
+        if luminance > 0.0 {
+            self.base_colour / luminance
+        } else {
+            Colour::new(1.0, 1.0, 1.0)
+        }
+    }
+
+    //reflectance at normal incidence
+    fn specular_colour(&self) -> Colour {
+        let white = Colour::new(1.0, 1.0, 1.0);
+        let dielectric = 0.08 * self.specular * lerp(white, self.tint(), self.specular_tint);
+        lerp(dielectric, self.base_colour, self.metallic)
+    }
+
+    fn sheen_colour(&self) -> Colour {
+        lerp(Colour::new(1.0, 1.0, 1.0), self.tint(), 0.5)
+    }
+
+    fn clearcoat_alpha(&self) -> f64 {
+        0.1 + (0.001 - 0.1) * self.clearcoat_gloss
+    }
+}
+
+fn schlick_weight(cosine: f64) -> f64 {
+    (1.0 - cosine).clamp(0.0, 1.0).powi(5)
+}
+
+//generalised Trowbridge-Reitz with gamma 1, which has the long tails of a clearcoat highlight
+fn gtr1(cos_h: f64, alpha: f64) -> f64 {
+    if alpha >= 1.0 {
+        return 1.0 / PI;
+    }
+    let a2 = alpha * alpha;
+    (a2 - 1.0) / (PI * a2.ln() * (1.0 + (a2 - 1.0) * cos_h * cos_h))
+}
+
+fn luminance(colour: Colour) -> f64 {
+    0.2126 * colour.e[0] + 0.7152 * colour.e[1] + 0.0722 * colour.e[2]
+}
+
+fn lerp(a: Colour, b: Colour, t: f64) -> Colour {
+    (1.0 - t) * a + t * b
+}
@@ -1,6 +1,16 @@
-use crate::material::{Colour, Material};
+use crate::aabb::Aabb;
+use crate::bvh::Bvh;
+use crate::lpe::Event;
+use crate::material::{white_balance, Colour, Material};
+use crate::medium::Fog;
+use crate::sky::Sky;
+use crate::texture::{Cutout, Image};
 use crate::vec3::{Point, Vec3};
 
+use std::sync::Arc;
+
+use rand::Rng;
+
 #[derive(Clone, Copy)]
 pub struct Ray {
     pub origin: Point,
@@ -10,50 +20,275 @@ impl Ray {
     pub fn new(origin: Point, direction: Vec3) -> Self {
         Self { origin, direction }
     }
-    fn at(self, t: f64) -> Point {
+    pub(crate) fn at(self, t: f64) -> Point {
         self.origin + t * self.direction
     }
     pub fn colour(self, world: &HittableList, max_depth: usize) -> Colour {
-        if max_depth == 0 {
-            return Colour::new(0.0, 0.0, 0.0);
+        let mut colour = Colour::default();
+        self.trace(world, max_depth, |_, light| colour = colour + light);
+        colour
+    }
+
+    //follows the ray out into the world, handing each light it finds to `light` along with the events that
+    //brought it back, camera first; between them they add up to `colour`
+    pub(crate) fn trace(
+        self,
+        world: &HittableList,
+        max_depth: usize,
+        mut light: impl FnMut(&[Event], Colour),
+    ) {
+        let mut ray = self;
+        let mut throughput = Colour::new(1.0, 1.0, 1.0);
+        let mut path = vec![];
+        //how likely the last bounce was to send the ray where it went, when the sun was also sampled from there
+        let mut scatter_pdf = None;
+        for _ in 0..max_depth {
+            let hit = world.hit(ray, 0.00001, f64::INFINITY);
+            //a scene-wide medium may scatter the ray before it reaches the next surface
+            let hit = match &world.fog {
+                Some(fog) => fog
+                    .hit(ray, 0.00001, hit.map_or(f64::INFINITY, |rec| rec.t))
+                    .or(hit),
+                None => hit,
+            };
+            let rec = match hit {
+                Some(rec) => rec,
+                None => return light(&path, throughput * ray.background(world, scatter_pdf)),
+            };
+
+            let emitted = rec.emitted + rec.material.emitted(&rec);
+            if emitted != Colour::default() {
+                light(&path, throughput * emitted);
+            }
+            let (attenuation, scattered, event) = match rec.material.scatter(ray, &rec) {
+                Some(scatter) => scatter,
+                None => return,
+            };
+            //a perturbed shading normal can send light through the surface it should bounce off, or back
+            //off one it should pass through; that light would leak, so drop it
+            let shading_side = Vec3::dot(scattered.direction, rec.normal) > 0.0;
+            let geometric_side = Vec3::dot(scattered.direction, rec.geometric_normal) > 0.0;
+            if shading_side != geometric_side {
+                return;
+            }
+
+            scatter_pdf = None;
+            if world.sky.is_some() || !world.lights.is_empty() {
+                if let Some((_, pdf)) = rec.material.evaluate(ray, &rec, scattered.direction) {
+                    if let Some(sky) = &world.sky {
+                        ray.sample_sun(world, sky, &rec, throughput, &mut path, &mut light);
+                        scatter_pdf = Some(pdf);
+                    }
+                    for point_light in &world.lights {
+                        ray.sample_light(
+                            world,
+                            point_light,
+                            &rec,
+                            throughput,
+                            &mut path,
+                            &mut light,
+                        );
+                    }
+                }
+            }
+
+            path.push(event);
+            throughput = throughput * attenuation;
+            ray = scattered;
+        }
+    }
+
+    //light straight from the sun to a hit the ray scattered off, weighed against finding it by scattering
+    fn sample_sun(
+        self,
+        world: &HittableList,
+        sky: &Sky,
+        rec: &HitRecord,
+        throughput: Colour,
+        path: &mut Vec<Event>,
+        light: &mut impl FnMut(&[Event], Colour),
+    ) {
+        let (direction, radiance, sun_pdf) = match sky.sample_sun() {
+            Some(sample) => sample,
+            None => return,
+        };
+        let (lobes, scatter_pdf) = match rec.material.evaluate(self, rec, direction) {
+            Some(evaluated) => evaluated,
+            None => return,
+        };
+        let shading_side = Vec3::dot(direction, rec.normal) > 0.0;
+        let geometric_side = Vec3::dot(direction, rec.geometric_normal) > 0.0;
+        if shading_side != geometric_side {
+            return;
+        }
+        let shadow = Ray::new(rec.p, direction);
+        let mut transmittance = world.transmittance(shadow, 0.00001, f64::INFINITY);
+        if let Some(fog) = &world.fog {
+            transmittance *= fog.transmittance(shadow, 0.00001, f64::INFINITY);
+        }
+        if transmittance <= 0.0 {
+            return;
+        }
+        let weight = transmittance * power_heuristic(sun_pdf, scatter_pdf) / sun_pdf;
+        for (lobe, event) in lobes {
+            if lobe != Colour::default() {
+                path.push(event);
+                light(path, throughput * lobe * radiance * weight);
+                path.pop();
+            }
+        }
+    }
+
+    //light straight from a point light to a hit the ray scattered off; scattering can never find such a light,
+    //so this is all of its light
+    fn sample_light(
+        self,
+        world: &HittableList,
+        point_light: &PointLight,
+        rec: &HitRecord,
+        throughput: Colour,
+        path: &mut Vec<Event>,
+        light: &mut impl FnMut(&[Event], Colour),
+    ) {
+        let to_light = point_light.position - rec.p;
+        let distance_squared = to_light.length_squared();
+        let intensity = point_light.intensity_towards(-to_light);
+        if intensity == Colour::default() || distance_squared <= 0.0 {
+            return;
+        }
+        let (lobes, _) = match rec.material.evaluate(self, rec, to_light) {
+            Some(evaluated) => evaluated,
+            None => return,
+        };
+        let shading_side = Vec3::dot(to_light, rec.normal) > 0.0;
+        let geometric_side = Vec3::dot(to_light, rec.geometric_normal) > 0.0;
+        if shading_side != geometric_side {
+            return;
         }
-        if let Some(rec) = world.hit(self, 0.00001, f64::INFINITY) {
-            if let Some((attentuation, scattered)) = rec.material.scatter(self, &rec) {
-                return attentuation * scattered.colour(world, max_depth - 1);
+        //the shadow ray reaches the light at t = 1
+        let shadow = Ray::new(rec.p, to_light);
+        let mut transmittance = world.transmittance(shadow, 0.00001, 1.0);
+        if let Some(fog) = &world.fog {
+            transmittance *= fog.transmittance(shadow, 0.00001, 1.0);
+        }
+        if transmittance <= 0.0 {
+            return;
+        }
+        let irradiance = transmittance * intensity / distance_squared;
+        for (lobe, event) in lobes {
+            if lobe != Colour::default() {
+                path.push(event);
+                light(path, throughput * lobe * irradiance);
+                path.pop();
             }
-            return Colour::new(0.0, 0.0, 0.0);
+        }
+    }
+
+    //the light from the sky, for rays that leave the world; with a sun that was also sampled directly from
+    //the last bounce, its share is weighed by how likely the bounce was to find it
+    fn background(self, world: &HittableList, scatter_pdf: Option<f64>) -> Colour {
+        if let Some(sky) = &world.sky {
+            let weight = scatter_pdf.map_or(1.0, |pdf| power_heuristic(pdf, sky.sun_pdf()));
+            return sky.radiance(self.direction) + weight * sky.sun_radiance(self.direction);
+        }
+        if let Some(background) = world.background {
+            return background;
         }
         let t = 0.5 * (self.direction.unit().e[1] + 1.0);
         (1.0 - t) * Colour::new(1.0, 1.0, 1.0) + t * Colour::new(0.5, 0.7, 1.0)
     }
 }
 
-#[derive(Clone, Copy, Default)]
-pub struct HitRecord {
+//Veach's weight for a sample taken with density `a` that could also have come from one with density `b`
+fn power_heuristic(a: f64, b: f64) -> f64 {
+    a * a / (a * a + b * b)
+}
+
+#[derive(Clone, Copy)]
+pub struct HitRecord<'a> {
     pub p: Point,
-    pub normal: Vec3,
-    t: f64,
-    material: Material,
+    pub normal: Vec3, //shading normal, on the same side as the geometric normal
+    pub geometric_normal: Vec3,
+    pub(crate) t: f64,
+    pub(crate) material: &'a Material,
     pub front_face: bool,
+    pub(crate) emitted: Colour,
+    pub u: f64,
+    pub v: f64,
+    //directions of increasing u and v along the surface, perpendicular to the outward normal
+    pub tangent: Vec3,
+    pub bitangent: Vec3,
+    //interpolated from a mesh's per-vertex colours, if it has them
+    pub vertex_colour: Option<Colour>,
+    //the shading normal before a normal or bump map tilted it, so another material's can replace it
+    pub(crate) undetailed_normal: Option<Vec3>,
+    //the scene graph node it belongs to, for picking and per-object passes
+    pub object_id: Option<usize>,
 }
-impl HitRecord {
-    fn front_face(r: Ray, normal: Vec3) -> bool {
+impl HitRecord<'_> {
+    pub(crate) fn front_face(r: Ray, normal: Vec3) -> bool {
         Vec3::dot(r.direction, normal) < 0.0
     }
+
+    //applies the material's normal or bump map, if any, to the shading normal, undoing any earlier material's
+    pub(crate) fn with_detail(mut self) -> Self {
+        if let Some(normal) = self.undetailed_normal.take() {
+            self.normal = normal;
+        }
+        if let Some(detail) = self.material.detail() {
+            self.undetailed_normal = Some(self.normal);
+            let outward = if self.front_face {
+                self.normal
+            } else {
+                -self.normal
+            };
+            let perturbed = detail.perturb(&self, outward);
+            self.normal = if self.front_face {
+                perturbed
+            } else {
+                -perturbed
+            };
+        }
+        self
+    }
 }
 
-trait Hittable {
-    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord>;
+pub trait Hittable: Send + Sync {
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>>;
+
+    //every crossing of the surface, nearest first; for closed shapes these alternate between entering and leaving
+    fn crossings(&self, r: Ray, t_min: f64, t_max: f64) -> Vec<HitRecord<'_>> {
+        let mut crossings = vec![];
+        let mut t_min = t_min;
+        while let Some(rec) = self.hit(r, t_min, t_max) {
+            t_min = rec.t + 1e-9 * rec.t.abs().max(1.0);
+            crossings.push(rec);
+        }
+        crossings
+    }
+
+    //None for things without finite extent, which the BVH can't hold
+    fn bounding_box(&self) -> Option<Aabb>;
+
+    //how much light gets from t_min to t_max along the ray: none past a surface, and for media an unbiased
+    //estimate, for shadow rays that only need to know how much of a light they see
+    fn transmittance(&self, r: Ray, t_min: f64, t_max: f64) -> f64 {
+        match self.hit(r, t_min, t_max) {
+            Some(_) => 0.0,
+            None => 1.0,
+        }
+    }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub struct Sphere {
     centre: Point,
     radius: f64,
     material: Material,
+    cutout: Option<Cutout>,
 }
 impl Hittable for Sphere {
-    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
         let oc = r.origin - self.centre;
 
         //compute quadratic equation coefficients
@@ -66,25 +301,50 @@ impl Hittable for Sphere {
         }
         let sqrtd = discriminant.sqrt();
 
-        //Find the nearest root that lies in the acceptable range
-        let mut root = (-half_b - sqrtd) / a;
-        if root < t_min || root > t_max {
-            root = (-half_b + sqrtd) / a;
+        //Find the nearest root that lies in the acceptable range, and on a part of the surface that's there
+        for root in [(-half_b - sqrtd) / a, (-half_b + sqrtd) / a] {
             if root < t_min || root > t_max {
-                return None;
+                continue;
+            }
+
+            let normal = (r.at(root) - self.centre) / self.radius;
+            let front_face = HitRecord::front_face(r, normal);
+            let (u, v) = Sphere::uv((r.at(root) - self.centre).unit());
+            if let Some(cutout) = &self.cutout {
+                if !cutout.is_opaque(u, v) {
+                    continue;
+                }
             }
+            let (tangent, bitangent) = Sphere::tangents(normal);
+            let normal = if front_face { normal } else { -normal };
+
+            return Some(
+                HitRecord {
+                    t: root,
+                    p: r.at(root),
+                    normal,
+                    geometric_normal: normal,
+                    material: &self.material,
+                    front_face,
+                    emitted: Colour::default(),
+                    u,
+                    v,
+                    tangent,
+                    bitangent,
+                    vertex_colour: None,
+                    undetailed_normal: None,
+                    object_id: None,
+                }
+                .with_detail(),
+            );
         }
 
-        let normal = (r.at(root) - self.centre) / self.radius;
-        let front_face = HitRecord::front_face(r, normal);
+        None
+    }
 
-        Some(HitRecord {
-            t: root,
-            p: r.at(root),
-            normal: if front_face { normal } else { -normal },
-            material: self.material,
-            front_face,
-        })
+    fn bounding_box(&self) -> Option<Aabb> {
+        let extent = Vec3::new(1.0, 1.0, 1.0) * self.radius.abs();
+        Some(Aabb::new(self.centre - extent, self.centre + extent))
     }
 }
 impl Sphere {
@@ -93,45 +353,308 @@ impl Sphere {
             centre,
             radius,
             material,
+            cutout: None,
+        }
+    }
+
+    pub fn with_cutout(mut self, cutout: Cutout) -> Self {
+        self.cutout = Some(cutout);
+        self
+    }
+
+    //latitude and longitude of a point on the unit sphere, scaled to [0, 1]
+    fn uv(p: Point) -> (f64, f64) {
+        let theta = (-p.e[1]).acos();
+        let phi = (-p.e[2]).atan2(p.e[0]) + std::f64::consts::PI;
+        (
+            phi / (2.0 * std::f64::consts::PI),
+            theta / std::f64::consts::PI,
+        )
+    }
+
+    //along lines of longitude and latitude, following increasing u and v
+    fn tangents(normal: Vec3) -> (Vec3, Vec3) {
+        let tangent = Vec3::new(normal.e[2], 0.0, -normal.e[0]);
+        if tangent.is_near_zero() {
+            //at the poles every direction is along a line of longitude
+            return normal.basis();
+        }
+        let tangent = tangent.unit();
+        (tangent, Vec3::cross(normal, tangent))
+    }
+}
+
+//light from a single point, which only surfaces that scatter light about can show; narrowed to a cone for a
+//spotlight
+#[derive(Clone, Copy, Debug)]
+pub struct PointLight {
+    position: Point,
+    intensity: Colour,              //per steradian
+    cone: Option<(Vec3, f64, f64)>, //unit axis, and cosines of the angles where it's gone and where it starts fading
+}
+impl PointLight {
+    pub fn new(position: Point, intensity: Colour) -> Self {
+        Self {
+            position,
+            intensity,
+            cone: None,
+        }
+    }
+
+    //shines only within `angle` degrees of `direction`, fading out over the outermost `fade` degrees as pbrt's
+    //spotlights do
+    pub fn with_cone(mut self, direction: Vec3, angle: f64, fade: f64) -> Self {
+        let fade = fade.clamp(0.0, angle);
+        self.cone = Some((
+            direction.unit(),
+            angle.to_radians().cos(),
+            (angle - fade).to_radians().cos(),
+        ));
+        self
+    }
+
+    //the light leaving along a direction away from the light
+    pub fn intensity_towards(&self, direction: Vec3) -> Colour {
+        let (axis, cos_outer, cos_inner) = match self.cone {
+            Some(cone) => cone,
+            None => return self.intensity,
+        };
+        let cosine = Vec3::dot(direction.unit(), axis);
+        if cosine < cos_outer {
+            Colour::default()
+        } else if cosine >= cos_inner {
+            self.intensity
+        } else {
+            self.intensity * ((cosine - cos_outer) / (cos_inner - cos_outer)).powi(4)
         }
     }
 }
 
 #[derive(Default, Clone)]
 pub struct HittableList {
-    objects: Vec<Sphere>,
+    objects: Vec<Arc<dyn Hittable>>,
+    bvh: Option<(Bvh, usize)>, //hierarchy over the bounded objects, and where the unbounded ones start
+    fog: Option<Fog>,
+    background: Option<Colour>,
+    sky: Option<Sky>,
+    lights: Vec<PointLight>,
 }
 impl HittableList {
-    pub fn add(&mut self, new: Sphere) {
-        self.objects.push(new)
+    pub fn add<H: Hittable + 'static>(&mut self, new: H) {
+        self.objects.push(Arc::new(new));
+        self.bvh = None;
+    }
+
+    //fills all empty space in the scene with a homogeneous medium
+    pub fn set_fog(&mut self, fog: Fog) {
+        self.fog = Some(fog)
+    }
+
+    //uniform light from every direction, in place of the sky gradient
+    pub fn set_background(&mut self, background: Colour) {
+        self.background = Some(background)
+    }
+
+    //daylight from a physical sun and sky, in place of the sky gradient or any background
+    pub fn set_sky(&mut self, sky: Sky) {
+        self.sky = Some(sky)
+    }
+
+    //a light too small to hit, which every surface and volume samples directly except perfectly smooth glass,
+    //and mirrors only show as a vanishingly small highlight
+    pub fn add_light(&mut self, light: PointLight) {
+        self.lights.push(light)
+    }
+
+    //speeds up hits on big scenes; adding more objects undoes this until it's called again
+    pub fn build_bvh(&mut self) {
+        let mut bounded = vec![];
+        let mut boxes = vec![];
+        let mut unbounded = vec![];
+        for (index, object) in self.objects.iter().enumerate() {
+            match object.bounding_box() {
+                Some(bounds) => {
+                    bounded.push(index);
+                    boxes.push(bounds);
+                }
+                None => unbounded.push(index),
+            }
+        }
+
+        //the hierarchy indexes into `bounded`, so store objects in that order followed by the rest
+        let objects = bounded
+            .iter()
+            .chain(&unbounded)
+            .map(|&index| self.objects[index].clone())
+            .collect();
+        self.objects = objects;
+        self.bvh = Some((Bvh::new(&boxes), bounded.len()));
     }
 }
 impl Hittable for HittableList {
-    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
-        let mut hit_anything = false;
-        let mut closest = HitRecord {
-            t: t_max,
-            ..Default::default()
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let (mut closest, rest) = match &self.bvh {
+            Some((bvh, first_unbounded)) => (
+                bvh.traverse(r, t_min, t_max, |index, t_max| {
+                    self.objects[index]
+                        .hit(r, t_min, t_max)
+                        .map(|rec| (rec.t, rec))
+                }),
+                &self.objects[*first_unbounded..],
+            ),
+            None => (None, &self.objects[..]),
         };
+        let mut closest_t = closest.map_or(t_max, |rec: HitRecord| rec.t);
 
-        for object in &self.objects {
-            closest = if let Some(closest) = object.hit(r, t_min, closest.t) {
-                hit_anything = true;
-                closest
-            } else {
-                closest
-            };
+        for object in rest {
+            if let Some(rec) = object.hit(r, t_min, closest_t) {
+                closest_t = rec.t;
+                closest = Some(rec);
+            }
         }
 
-        if hit_anything {
-            Some(closest)
-        } else {
-            None
+        closest
+    }
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.objects
+            .iter()
+            .map(|object| object.bounding_box())
+            .reduce(|a, b| Some(Aabb::surrounding(a?, b?)))
+            .flatten()
+    }
+
+    fn transmittance(&self, r: Ray, t_min: f64, t_max: f64) -> f64 {
+        let mut transmittance = 1.0;
+        let (blocked, rest) = match &self.bvh {
+            Some((bvh, first_unbounded)) => (
+                bvh.traverse(r, t_min, t_max, |index, _| {
+                    transmittance *= self.objects[index].transmittance(r, t_min, t_max);
+                    //nothing gets past something opaque; an empty interval ends the traversal
+                    (transmittance <= 0.0).then_some((f64::NEG_INFINITY, ()))
+                })
+                .is_some(),
+                &self.objects[*first_unbounded..],
+            ),
+            None => (false, &self.objects[..]),
+        };
+        if blocked {
+            return 0.0;
+        }
+        for object in rest {
+            transmittance *= object.transmittance(r, t_min, t_max);
+            if transmittance <= 0.0 {
+                return 0.0;
+            }
         }
+        transmittance
     }
 }
 
-#[derive(Clone, Copy)]
+//how points on the image map to directions from the camera
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    Perspective,       //straight lines stay straight; `Camera::new`'s vfov sets the view
+    Orthographic(f64), //parallel rays across a view this tall, for technical drawings
+    Equidistant(f64), //fisheye whose angle off axis grows evenly outwards; degrees across its circle
+    Equisolid(f64), //fisheye keeping areas in proportion, like most real ones; degrees across its circle
+    Equirectangular, //longitude across and latitude up, the whole sphere around the camera
+}
+
+//the shape of the lens opening, which out-of-focus highlights take on
+#[derive(Clone)]
+pub enum Aperture {
+    Circle,
+    Polygon(usize, f64), //straight-edged iris; number of blades, and their rotation in degrees
+    Mask(Arc<ApertureMask>), //a cut-out in front of the lens, made with `Aperture::mask`
+}
+impl Aperture {
+    //lighter parts of the image let more light through; an image with alpha lets it through where it's opaque
+    pub fn mask(image: &Image) -> Self {
+        Self::Mask(Arc::new(ApertureMask::new(image)))
+    }
+
+    //a point on the opening, within the unit circle; None for a mask that lets nothing through
+    fn sample(&self) -> Option<(f64, f64)> {
+        let mut rng = rand::thread_rng();
+        Some(match *self {
+            Self::Circle => {
+                let p = Vec3::random_in_unit_disk();
+                (p.e[0], p.e[1])
+            }
+            Self::Polygon(blades, rotation) => {
+                //the triangles between the middle and each edge are all the same size, so pick one evenly
+                let step = 2.0 * std::f64::consts::PI / blades.max(3) as f64;
+                let start = rotation.to_radians() + step * rng.gen_range(0..blades.max(3)) as f64;
+                let (mut a, mut b) = (rng.gen::<f64>(), rng.gen::<f64>());
+                if a + b > 1.0 {
+                    (a, b) = (1.0 - a, 1.0 - b);
+                }
+                let (end_sin, end_cos) = (start + step).sin_cos();
+                (a * start.cos() + b * end_cos, a * start.sin() + b * end_sin)
+            }
+            Self::Mask(ref mask) => mask.sample()?,
+        })
+    }
+}
+
+//an aperture mask's pixels within the unit circle, each picked in proportion to how much light it lets
+//through, so dark masks are sampled as readily as light ones
+pub struct ApertureMask {
+    width: usize,
+    height: usize,
+    cdf: Vec<f64>, //running totals of the pixels' openness, top row first
+}
+impl ApertureMask {
+    fn new(image: &Image) -> Self {
+        let (width, height) = (image.width(), image.height());
+        let mut total = 0.0;
+        let mut cdf = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                //pixels whose middle lies outside the lens don't count
+                let (cx, cy) = Self::position(width, height, x as f64 + 0.5, y as f64 + 0.5);
+                if cx * cx + cy * cy <= 1.0 {
+                    total += image.pixel_opacity(x, y).max(0.0);
+                }
+                cdf.push(total);
+            }
+        }
+        Self { width, height, cdf }
+    }
+
+    //where a point in pixel units lands on the lens, with the image spanning the square around the unit circle
+    fn position(width: usize, height: usize, x: f64, y: f64) -> (f64, f64) {
+        (2.0 * x / width as f64 - 1.0, 1.0 - 2.0 * y / height as f64)
+    }
+
+    fn sample(&self) -> Option<(f64, f64)> {
+        let total = *self.cdf.last()?;
+        if total <= 0.0 {
+            return None;
+        }
+        let mut rng = rand::thread_rng();
+        //edge pixels straddle the circle, so points from their outside part are picked again
+        loop {
+            let target = rng.gen::<f64>() * total;
+            let index = self
+                .cdf
+                .partition_point(|&sum| sum <= target)
+                .min(self.cdf.len() - 1);
+            let (x, y) = (index % self.width, index / self.width);
+            let (px, py) = Self::position(
+                self.width,
+                self.height,
+                x as f64 + rng.gen::<f64>(),
+                y as f64 + rng.gen::<f64>(),
+            );
+            if px * px + py * py <= 1.0 {
+                return Some((px, py));
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct Camera {
     origin: Point,
     lower_left_corner: Point,
@@ -141,6 +664,17 @@ pub struct Camera {
     v: Vec3,
     w: Vec3,
     lens_radius: f64,
+    focus_dist: f64,
+    aspect_ratio: f64,
+    projection: Projection,
+    eye: f64,         //distance right of the middle of the head, for one of a stereo pair
+    convergence: f64, //distance at which the eyes' views meet, where stereo images line up
+    aperture: Aperture,
+    squeeze: f64, //how much taller than wide the aperture is
+    cat_eye: f64, //how far the lens barrel cuts into the aperture at the corners of the frame
+    exposure: f64,
+    vignetting: f64,
+    white_balance: [Vec3; 3], //rows of the matrix every colour is developed through
 }
 impl Camera {
     pub fn new(
@@ -173,16 +707,305 @@ impl Camera {
             v,
             w,
             lens_radius: aperture / 2.0,
+            focus_dist,
+            aspect_ratio,
+            projection: Projection::Perspective,
+            eye: 0.0,
+            convergence: f64::INFINITY,
+            aperture: Aperture::Circle,
+            squeeze: 1.0,
+            cat_eye: 0.0,
+            exposure: 1.0,
+            vignetting: 0.0,
+            white_balance: [
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+                Vec3::new(0.0, 0.0, 1.0),
+            ],
+        }
+    }
+
+    //the same camera moved, pointed and refocused as `new` would set it up, keeping its projection, aperture
+    //shape, stereo eye and how it develops light; for cameras that move from frame to frame
+    pub fn with_view(
+        self,
+        origin: Point,
+        focus: Vec3,
+        vup: Vec3,
+        vfov: f64,
+        aperture: f64,
+        focus_dist: f64,
+    ) -> Self {
+        let view = Self::new(
+            origin,
+            focus,
+            vup,
+            vfov,
+            self.aspect_ratio,
+            aperture,
+            focus_dist,
+        );
+        Self {
+            origin: view.origin,
+            lower_left_corner: view.lower_left_corner,
+            horizontal: view.horizontal,
+            vertical: view.vertical,
+            u: view.u,
+            v: view.v,
+            w: view.w,
+            lens_radius: view.lens_radius,
+            focus_dist: view.focus_dist,
+            ..self
         }
     }
 
-    pub fn get_ray(self, s: f64, t: f64) -> Ray {
-        let rd = self.lens_radius * Vec3::random_in_unit_disk();
-        let offset = self.u * rd.e[0] + self.v * rd.e[1];
+    //the same position, orientation and lens with a different projection; only perspective uses vfov
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self
+    }
+
+    //one eye of a stereo pair, `offset` to the right of this camera (negative for the left eye) and looking
+    //at the same things `convergence` away; an infinite convergence keeps the eyes looking straight ahead
+    pub fn with_eye(mut self, offset: f64, convergence: f64) -> Self {
+        self.eye = offset;
+        self.convergence = convergence;
+        self
+    }
 
-        Ray::new(
-            self.origin + offset,
-            self.lower_left_corner + s * self.horizontal + t * self.vertical - self.origin - offset,
-        )
+    //the shape of out-of-focus highlights; the aperture passed to `new` sets its size
+    pub fn with_aperture(mut self, aperture: Aperture) -> Self {
+        self.aperture = aperture;
+        self
+    }
+
+    //stretches the aperture vertically, keeping its area, to give the oval highlights of anamorphic lenses
+    pub fn with_squeeze(mut self, squeeze: f64) -> Self {
+        self.squeeze = squeeze;
+        self
+    }
+
+    //off axis, the lens barrel hides part of the aperture, so highlights near the edges of the frame narrow
+    //into cat's eyes and the corners darken; 0 is none, and at 1 the corners are half covered
+    pub fn with_cat_eye(mut self, strength: f64) -> Self {
+        self.cat_eye = strength;
+        self
+    }
+
+    //exposes like a full-frame camera, reading scene units as metres and colours as luminances in cd/m²;
+    //`shutter` is in seconds, and the f-number sets the lens radius from the focal length the fov implies
+    pub fn with_exposure(mut self, iso: f64, shutter: f64, f_number: f64) -> Self {
+        const SENSOR_HEIGHT: f64 = 0.024;
+        let focal_length = SENSOR_HEIGHT * self.focus_dist / self.vertical.length();
+        self.lens_radius = focal_length / f_number / 2.0;
+        //saturation-based sensitivity: the luminance that just reaches white is 1.2 * 2^EV100, where
+        //EV100 = log2(N² / t * 100 / iso)
+        self.exposure = iso * shutter / (120.0 * f_number * f_number);
+        self
+    }
+
+    //natural falloff towards the corners: 1 follows the cos⁴ law of a simple lens, 0 is none
+    pub fn with_vignetting(mut self, strength: f64) -> Self {
+        self.vignetting = strength;
+        self
+    }
+
+    //light of this colour temperature comes out white; 6500K leaves colours as they are
+    pub fn with_white_balance(mut self, kelvin: f64) -> Self {
+        self.white_balance = white_balance(kelvin);
+        self
+    }
+
+    //how far in front of the camera a point is: along the view axis for flat projections, and straight-line
+    //distance for wide ones, which look every way at once
+    pub fn depth(&self, p: Point) -> f64 {
+        match self.projection {
+            Projection::Perspective | Projection::Orthographic(_) => {
+                Vec3::dot(p - self.origin, -self.w)
+            }
+            _ => (p - self.origin).length(),
+        }
+    }
+
+    //the light arriving at (s, t), scaled as the camera's settings record it, ready for tone mapping
+    pub fn develop(&self, colour: Colour, s: f64, t: f64) -> Colour {
+        let mut scale = self.exposure;
+        if self.vignetting != 0.0 {
+            let through =
+                self.lower_left_corner + s * self.horizontal + t * self.vertical - self.origin;
+            scale *= Vec3::dot(through.unit(), -self.w).powf(4.0 * self.vignetting);
+        }
+        let [r, g, b] = self
+            .white_balance
+            .map(|row| Vec3::dot(row, colour).max(0.0));
+        scale * Colour::new(r, g, b)
+    }
+
+    //where on the lens a ray through (s, t) passes, scaled to the lens radius; None if the barrel blocks it or a mask lets nothing through
+    fn lens(&self, s: f64, t: f64) -> Option<(f64, f64)> {
+        if self.lens_radius == 0.0 {
+            return Some((0.0, 0.0));
+        }
+        let (x, y) = self.aperture.sample()?;
+        //the barrel's opening seen from off axis is a circle shifted towards the middle of the frame
+        let (cx, cy) = (
+            self.cat_eye * (2.0 * s - 1.0),
+            self.cat_eye * (2.0 * t - 1.0),
+        );
+        if (x + cx).powi(2) + (y + cy).powi(2) > 1.0 {
+            return None;
+        }
+        let stretch = self.squeeze.sqrt();
+        Some((
+            self.lens_radius * x / stretch,
+            self.lens_radius * y * stretch,
+        ))
+    }
+
+    //s and t run from 0 to 1 across and up the image; None where the projection doesn't reach, such as
+    //outside a fisheye's circle
+    pub fn get_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        let forward = -self.w;
+        let (eye, direction) = match self.projection {
+            Projection::Perspective => {
+                let (x, y) = self.lens(s, t)?;
+                let offset = self.u * x + self.v * y;
+                let eye = self.eye * self.u;
+                //slide the window back towards the middle so the eyes' views meet at the convergence distance
+                let window = eye * (1.0 - self.focus_dist / self.convergence);
+
+                return Some(Ray::new(
+                    self.origin + eye + offset,
+                    self.lower_left_corner + window + s * self.horizontal + t * self.vertical
+                        - self.origin
+                        - eye
+                        - offset,
+                ));
+            }
+            Projection::Orthographic(height) => {
+                let across =
+                    (s - 0.5) * height * self.aspect_ratio * self.u + (t - 0.5) * height * self.v;
+                let (x, y) = self.lens(s, t)?;
+                let offset = self.u * x + self.v * y;
+
+                //each ray gets its own lens, focused on the same plane as a perspective camera's
+                return Some(Ray::new(
+                    self.origin + self.eye * self.u + across + offset,
+                    self.focus_dist * forward - offset,
+                ));
+            }
+            Projection::Equidistant(fov) | Projection::Equisolid(fov) => {
+                //the image circle fills the height of the image
+                let (x, y) = ((2.0 * s - 1.0) * self.aspect_ratio, 2.0 * t - 1.0);
+                let r = (x * x + y * y).sqrt();
+                if r > 1.0 {
+                    return None;
+                }
+                let half = 0.5 * fov.to_radians();
+                let theta = match self.projection {
+                    Projection::Equidistant(_) => r * half,
+                    _ => 2.0 * (r * (0.5 * half).sin()).asin(),
+                };
+                let phi = y.atan2(x);
+                (
+                    self.eye * self.u,
+                    theta.sin() * (phi.cos() * self.u + phi.sin() * self.v) + theta.cos() * forward,
+                )
+            }
+            Projection::Equirectangular => {
+                let longitude = (s - 0.5) * 2.0 * std::f64::consts::PI;
+                let latitude = (t - 0.5) * std::f64::consts::PI;
+                //omni-directional stereo: the eyes circle the middle, always either side of the way they look;
+                //looking straight up or down there's no telling which side that is, so the eyes close in
+                let right = longitude.cos() * self.u - longitude.sin() * forward;
+                (
+                    self.eye * latitude.cos() * right,
+                    latitude.cos() * (longitude.sin() * self.u + longitude.cos() * forward)
+                        + latitude.sin() * self.v,
+                )
+            }
+        };
+        //aim at where the middle of the head would see at the convergence distance
+        let direction = match self.convergence.is_finite() {
+            true => (self.convergence * direction - eye).unit(),
+            false => direction,
+        };
+
+        //wide views look every way at once, so give each ray a lens facing along it, all focused on a sphere
+        let (a, b) = direction.basis();
+        let (x, y) = self.lens(s, t)?;
+        let offset = a * x + b * y;
+        Some(Ray::new(
+            self.origin + eye + offset,
+            self.focus_dist * direction - offset,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::blackbody;
+
+    #[test]
+    fn dark_masks_keep_their_shape() {
+        //a faint opening off to one side of an otherwise black 8x8 mask
+        let mut pixels = vec![Colour::default(); 64];
+        pixels[2 * 8 + 5] = Colour::new(0.001, 0.001, 0.001);
+        let mask = Aperture::mask(&Image::new(8, 8, pixels));
+        for _ in 0..1000 {
+            let (x, y) = mask.sample().unwrap();
+            assert!((0.25..=0.5).contains(&x) && (0.25..=0.5).contains(&y));
+        }
+    }
+
+    #[test]
+    fn white_balance_stays_finite_for_candlelight() {
+        let camera = Camera::new(
+            Point::default(),
+            Point::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            1.0,
+            0.0,
+            1.0,
+        );
+        for kelvin in [1000.0, 1500.0, 1900.0] {
+            let balanced = camera.clone().with_white_balance(kelvin);
+            for row in balanced.white_balance {
+                assert!(row.e.iter().all(|gain| gain.is_finite()));
+            }
+            let black = balanced.develop(Colour::default(), 0.5, 0.5);
+            assert_eq!(black, Colour::default());
+            let blue = balanced.develop(Colour::new(0.0, 0.0, 0.1), 0.5, 0.5);
+            assert!(blue.e.iter().all(|c| c.is_finite() && *c < 2.0));
+        }
+    }
+
+    #[test]
+    fn white_balance_turns_its_light_into_daylight() {
+        let camera = Camera::new(
+            Point::default(),
+            Point::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            1.0,
+            0.0,
+            1.0,
+        );
+        for kelvin in [2700.0, 4000.0, 6500.0, 10000.0] {
+            let developed =
+                camera
+                    .clone()
+                    .with_white_balance(kelvin)
+                    .develop(blackbody(kelvin), 0.5, 0.5);
+            assert!((developed - blackbody(6500.0)).length() < 0.01);
+        }
+    }
+
+    #[test]
+    fn closed_masks_let_nothing_through() {
+        let mask = Aperture::mask(&Image::new(4, 4, vec![Colour::default(); 16]));
+        assert!(mask.sample().is_none());
     }
 }
@@ -0,0 +1,296 @@
+//a hierarchy of named objects that can be found, moved, hidden and restyled after they're built, then
+//flattened into a `HittableList` to render
+
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    material::Material,
+    ray::{HitRecord, Hittable, HittableList, Ray},
+    transform::Transform,
+    Point, Vec3,
+};
+
+//indexes nodes in the graph they came from; it's also the object ID given to their hits
+pub type NodeId = usize;
+
+struct Node {
+    name: String,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    transform: Transform, //relative to the parent
+    visible: bool,
+    material: Option<Material>, //replaces the materials of everything below
+    object: Option<Arc<dyn Hittable>>,
+}
+
+#[derive(Default)]
+pub struct SceneGraph {
+    nodes: Vec<Node>,
+}
+impl SceneGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    //an empty node for grouping others under
+    pub fn add_group(&mut self, name: &str, parent: Option<NodeId>) -> NodeId {
+        self.push(name, parent, None)
+    }
+
+    pub fn add<H: Hittable + 'static>(
+        &mut self,
+        name: &str,
+        parent: Option<NodeId>,
+        object: H,
+    ) -> NodeId {
+        self.push(name, parent, Some(Arc::new(object)))
+    }
+
+    fn push(
+        &mut self,
+        name: &str,
+        parent: Option<NodeId>,
+        object: Option<Arc<dyn Hittable>>,
+    ) -> NodeId {
+        let id = self.nodes.len();
+        if let Some(parent) = parent {
+            self.nodes[parent].children.push(id);
+        }
+        self.nodes.push(Node {
+            name: name.to_string(),
+            parent,
+            children: vec![],
+            transform: Transform::identity(),
+            visible: true,
+            material: None,
+            object,
+        });
+        id
+    }
+
+    //the first node with this name, in the order they were added
+    pub fn find(&self, name: &str) -> Option<NodeId> {
+        self.nodes.iter().position(|node| node.name == name)
+    }
+
+    pub fn name(&self, id: NodeId) -> &str {
+        &self.nodes[id].name
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id].parent
+    }
+
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        &self.nodes[id].children
+    }
+
+    pub fn transform(&self, id: NodeId) -> Transform {
+        self.nodes[id].transform
+    }
+
+    pub fn set_transform(&mut self, id: NodeId, transform: Transform) {
+        self.nodes[id].transform = transform;
+    }
+
+    //from the node's space to the world's, through all its ancestors
+    pub fn world_transform(&self, id: NodeId) -> Transform {
+        let node = &self.nodes[id];
+        match node.parent {
+            Some(parent) => self.world_transform(parent) * node.transform,
+            None => node.transform,
+        }
+    }
+
+    pub fn is_visible(&self, id: NodeId) -> bool {
+        self.nodes[id].visible
+    }
+
+    //hiding a node hides everything below it too
+    pub fn set_visible(&mut self, id: NodeId, visible: bool) {
+        self.nodes[id].visible = visible;
+    }
+
+    //None restores the objects' own materials, unless an ancestor overrides them
+    pub fn set_material(&mut self, id: NodeId, material: Option<Material>) {
+        self.nodes[id].material = material;
+    }
+
+    //every visible object in place, its hits tagged with its node's ID
+    pub fn flatten(&self) -> HittableList {
+        let mut world = HittableList::default();
+        let roots = (0..self.nodes.len()).filter(|&id| self.nodes[id].parent.is_none());
+        let mut stack: Vec<_> = roots.map(|id| (id, Transform::identity(), None)).collect();
+        while let Some((id, parent, inherited)) = stack.pop() {
+            let node = &self.nodes[id];
+            if !node.visible {
+                continue;
+            }
+            let transform = parent * node.transform;
+            let material = node.material.as_ref().or(inherited);
+            if let Some(object) = &node.object {
+                world.add(Instance::new(
+                    object.clone(),
+                    transform,
+                    material.cloned(),
+                    id,
+                ));
+            }
+            stack.extend(
+                node.children
+                    .iter()
+                    .map(|&child| (child, transform, material)),
+            );
+        }
+        world
+    }
+}
+
+//an object moved into place by transforming rays into its own space and its hits back out
+struct Instance {
+    object: Arc<dyn Hittable>,
+    transform: Transform,
+    inverse: Option<Transform>, //None when the object is flattened to nothing
+    material: Option<Material>,
+    id: NodeId,
+}
+impl Instance {
+    fn new(
+        object: Arc<dyn Hittable>,
+        transform: Transform,
+        material: Option<Material>,
+        id: NodeId,
+    ) -> Self {
+        Self {
+            object,
+            transform,
+            inverse: transform.inverse(),
+            material,
+            id,
+        }
+    }
+}
+impl Hittable for Instance {
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        //the direction isn't renormalised, so distances along the ray are the same in both spaces
+        let inverse = self.inverse?;
+        let local = Ray::new(inverse.point(r.origin), inverse.vector(r.direction));
+        let mut rec = self.object.hit(local, t_min, t_max)?;
+        rec.p = self.transform.point(rec.p);
+        rec.normal = self.transform.normal(rec.normal).unit();
+        rec.geometric_normal = self.transform.normal(rec.geometric_normal).unit();
+        rec.undetailed_normal = rec
+            .undetailed_normal
+            .map(|normal| self.transform.normal(normal).unit());
+        //shears and uneven scales skew the tangents, so straighten them up against the normal and each other
+        let normal = rec.undetailed_normal.unwrap_or(rec.normal);
+        let tangent = self.transform.vector(rec.tangent);
+        let tangent = (tangent - Vec3::dot(tangent, normal) * normal).unit();
+        let bitangent = self.transform.vector(rec.bitangent);
+        let bitangent = bitangent
+            - Vec3::dot(bitangent, normal) * normal
+            - Vec3::dot(bitangent, tangent) * tangent;
+        rec.tangent = tangent;
+        rec.bitangent = bitangent.unit();
+        rec.object_id = Some(self.id);
+        Some(match &self.material {
+            Some(material) => {
+                rec.material = material;
+                rec.with_detail()
+            }
+            None => rec,
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let bounds = self.object.bounding_box()?;
+        let corners = (0..8).map(|i| {
+            let pick = |axis: usize| match i >> axis & 1 {
+                0 => bounds.min.e[axis],
+                _ => bounds.max.e[axis],
+            };
+            self.transform.point(Point::new(pick(0), pick(1), pick(2)))
+        });
+        Aabb::from_points(corners)
+    }
+
+    fn transmittance(&self, r: Ray, t_min: f64, t_max: f64) -> f64 {
+        match self.inverse {
+            Some(inverse) => self.object.transmittance(
+                Ray::new(inverse.point(r.origin), inverse.vector(r.direction)),
+                t_min,
+                t_max,
+            ),
+            None => 1.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        material::Colour,
+        ray::Sphere,
+        texture::{Detail, Image},
+    };
+
+    //a normal map of one colour, tilting every normal the same way in tangent space
+    fn normal_map(encoded: Colour) -> Detail {
+        Detail::NormalMap(Arc::new(Image::new(1, 1, vec![encoded])))
+    }
+
+    fn detailed(encoded: Colour) -> Material {
+        Material::Detailed(
+            Box::new(Material::Lambertian(Colour::new(0.5, 0.5, 0.5))),
+            normal_map(encoded),
+        )
+    }
+
+    #[test]
+    fn override_applies_its_own_detail_to_the_unperturbed_normal() {
+        //the sphere's own map lays the normal along the tangent; the override's map leaves it alone
+        let sphere = Sphere::new(Point::default(), 1.0, detailed(Colour::new(1.0, 0.5, 0.5)));
+        let flat = detailed(Colour::new(0.5, 0.5, 1.0));
+        let ray = Ray::new(Point::new(0.3, 0.2, 5.0), Vec3::new(0.0, 0.0, -1.0));
+
+        let own = Instance::new(Arc::new(sphere.clone()), Transform::identity(), None, 0);
+        let rec = own.hit(ray, 0.001, f64::INFINITY).unwrap();
+        assert!(Vec3::dot(rec.normal, rec.geometric_normal) < 0.9);
+
+        let overridden = Instance::new(Arc::new(sphere), Transform::identity(), Some(flat), 0);
+        let rec = overridden.hit(ray, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.normal - rec.geometric_normal).length() < 1e-9);
+
+        let plain = Material::Lambertian(Colour::new(0.5, 0.5, 0.5));
+        let sphere = Sphere::new(Point::default(), 1.0, detailed(Colour::new(1.0, 0.5, 0.5)));
+        let overridden = Instance::new(Arc::new(sphere), Transform::identity(), Some(plain), 0);
+        let rec = overridden.hit(ray, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.normal - rec.geometric_normal).length() < 1e-9);
+    }
+
+    #[test]
+    fn sheared_tangents_stay_orthonormal() {
+        let shear = Transform::from_rows([
+            [1.0, 0.8, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]) * Transform::scale(Vec3::new(1.0, 3.0, 0.5));
+        let sphere = Sphere::new(
+            Point::default(),
+            1.0,
+            Material::Lambertian(Colour::new(0.5, 0.5, 0.5)),
+        );
+        let instance = Instance::new(Arc::new(sphere), shear, None, 0);
+        let ray = Ray::new(Point::new(0.4, 0.7, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let rec = instance.hit(ray, 0.001, f64::INFINITY).unwrap();
+
+        assert!((rec.tangent.length() - 1.0).abs() < 1e-9);
+        assert!((rec.bitangent.length() - 1.0).abs() < 1e-9);
+        assert!(Vec3::dot(rec.tangent, rec.normal).abs() < 1e-9);
+        assert!(Vec3::dot(rec.bitangent, rec.normal).abs() < 1e-9);
+        assert!(Vec3::dot(rec.tangent, rec.bitangent).abs() < 1e-9);
+    }
+}
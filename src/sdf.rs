@@ -0,0 +1,204 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    material::{Colour, Material},
+    ray::{HitRecord, Hittable, Ray},
+    Point, Vec3,
+};
+
+//a signed distance function: negative inside, positive outside, and never more than the true distance
+#[derive(Clone)]
+pub enum Sdf {
+    Sphere(f64),                          //radius
+    RoundedBox(Vec3, f64),                //half extents, and a radius for its edges
+    Torus(f64, f64),                      //major and minor radius, around the y axis
+    Mandelbulb(f64, usize),               //power, usually 8, and iterations
+    Translate(Box<Sdf>, Vec3),            //moves the shape by the offset
+    Union(Box<Sdf>, Box<Sdf>),            //inside either
+    SmoothUnion(Box<Sdf>, Box<Sdf>, f64), //a blended join; its width
+    Difference(Box<Sdf>, Box<Sdf>),       //inside the first but not the second
+    Twist(Box<Sdf>, f64),                 //turns around the y axis; radians per unit of height
+    Repeat(Box<Sdf>, Vec3), //copies on a grid; the period along each axis, or 0 for none
+    Function(Arc<dyn Fn(Point) -> f64 + Send + Sync>),
+}
+impl Sdf {
+    pub fn distance(&self, p: Point) -> f64 {
+        match *self {
+            Self::Sphere(radius) => p.length() - radius,
+            Self::RoundedBox(half_extents, radius) => {
+                let q = Vec3::new(
+                    p.e[0].abs() - half_extents.e[0] + radius,
+                    p.e[1].abs() - half_extents.e[1] + radius,
+                    p.e[2].abs() - half_extents.e[2] + radius,
+                );
+                let outside = Vec3::new(q.e[0].max(0.0), q.e[1].max(0.0), q.e[2].max(0.0));
+                outside.length() + q.e[0].max(q.e[1]).max(q.e[2]).min(0.0) - radius
+            }
+            Self::Torus(major_radius, minor_radius) => {
+                let ring = (p.e[0] * p.e[0] + p.e[2] * p.e[2]).sqrt() - major_radius;
+                (ring * ring + p.e[1] * p.e[1]).sqrt() - minor_radius
+            }
+            Self::Mandelbulb(power, iterations) => mandelbulb(p, power, iterations),
+            Self::Translate(ref sdf, offset) => sdf.distance(p - offset),
+            Self::Union(ref a, ref b) => a.distance(p).min(b.distance(p)),
+            Self::SmoothUnion(ref a, ref b, width) => {
+                //polynomial smooth minimum
+                let (a, b) = (a.distance(p), b.distance(p));
+                let h = (0.5 + 0.5 * (b - a) / width).clamp(0.0, 1.0);
+                b + h * (a - b) - width * h * (1.0 - h)
+            }
+            Self::Difference(ref a, ref b) => a.distance(p).max(-b.distance(p)),
+            Self::Twist(ref sdf, rate) => {
+                let (sin, cos) = (rate * p.e[1]).sin_cos();
+                let q = Point::new(
+                    cos * p.e[0] - sin * p.e[2],
+                    p.e[1],
+                    sin * p.e[0] + cos * p.e[2],
+                );
+                //twisting stretches space by up to this much, so shrink the distance to stay conservative
+                let radius = (p.e[0] * p.e[0] + p.e[2] * p.e[2]).sqrt();
+                sdf.distance(q) / (1.0 + (rate * radius).powi(2)).sqrt()
+            }
+            Self::Repeat(ref sdf, period) => {
+                let wrap = |x: f64, period: f64| match period == 0.0 {
+                    true => x,
+                    false => x - period * (x / period).round(),
+                };
+                sdf.distance(Point::new(
+                    wrap(p.e[0], period.e[0]),
+                    wrap(p.e[1], period.e[1]),
+                    wrap(p.e[2], period.e[2]),
+                ))
+            }
+            Self::Function(ref f) => f(p),
+        }
+    }
+
+    //the outward direction, from the gradient by the tetrahedron technique
+    fn normal(&self, p: Point, h: f64) -> Vec3 {
+        [
+            Vec3::new(1.0, -1.0, -1.0),
+            Vec3::new(-1.0, -1.0, 1.0),
+            Vec3::new(-1.0, 1.0, -1.0),
+            Vec3::new(1.0, 1.0, 1.0),
+        ]
+        .iter()
+        .fold(Vec3::default(), |sum, &k| {
+            sum + self.distance(p + h * k) * k
+        })
+        .unit()
+    }
+}
+
+//an implicit surface, found by sphere tracing within the given bounds
+#[derive(Clone)]
+pub struct SdfShape {
+    sdf: Sdf,
+    bounds: Aabb,
+    material: Material,
+    epsilon: f64,
+    max_steps: usize,
+}
+impl SdfShape {
+    pub fn new(sdf: Sdf, bounds: Aabb, material: Material) -> Self {
+        Self {
+            sdf,
+            bounds,
+            material,
+            epsilon: 1e-4,
+            max_steps: 256,
+        }
+    }
+
+    //how close a ray must come to the surface to hit it
+    pub fn with_epsilon(mut self, epsilon: f64) -> Self {
+        self.epsilon = epsilon;
+        self
+    }
+
+    //rays that take more steps than this, usually by skimming past the surface, miss
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    fn record(&self, r: Ray, t: f64) -> HitRecord<'_> {
+        let p = r.at(t);
+        let outward = self.sdf.normal(p, self.epsilon);
+        let front_face = HitRecord::front_face(r, outward);
+        let normal = if front_face { outward } else { -outward };
+        let (tangent, bitangent) = outward.basis();
+
+        //implicit surfaces have no natural parameterisation, so textures should look things up by position
+        HitRecord {
+            t,
+            p,
+            normal,
+            geometric_normal: normal,
+            material: &self.material,
+            front_face,
+            emitted: Colour::default(),
+            u: 0.0,
+            v: 0.0,
+            tangent,
+            bitangent,
+            vertex_colour: None,
+            undetailed_normal: None,
+            object_id: None,
+        }
+        .with_detail()
+    }
+}
+impl Hittable for SdfShape {
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let (mut t, t_max) = self.bounds.intersect(r, t_min, t_max)?;
+        let inv_length = r.direction.length().recip();
+
+        //rays leaving the surface must get clear of it before they can hit it again
+        let mut clear = false;
+        for _ in 0..self.max_steps {
+            let distance = self.sdf.distance(r.at(t)).abs();
+            if distance >= self.epsilon {
+                clear = true;
+            } else if clear {
+                return Some(self.record(r, t));
+            }
+            t += distance.max(self.epsilon) * inv_length;
+            if t > t_max {
+                return None;
+            }
+        }
+        None
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.bounds)
+    }
+}
+//the distance estimate from the escape rate of the iterated point
+fn mandelbulb(p: Point, power: f64, iterations: usize) -> f64 {
+    let mut z = p;
+    let mut dr = 1.0;
+    let mut r = 0.0;
+    for _ in 0..iterations {
+        r = z.length();
+        if r > 2.0 {
+            break;
+        }
+        let theta = (z.e[2] / r).acos() * power;
+        let phi = z.e[1].atan2(z.e[0]) * power;
+        dr = r.powf(power - 1.0) * power * dr + 1.0;
+        z = r.powf(power)
+            * Vec3::new(
+                theta.sin() * phi.cos(),
+                theta.sin() * phi.sin(),
+                theta.cos(),
+            )
+            + p;
+    }
+    match r > 0.0 {
+        true => 0.5 * r.ln() * r / dr,
+        false => 0.0,
+    }
+}
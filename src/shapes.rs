@@ -0,0 +1,668 @@
+//Analytic primitives besides the sphere. Each is built around the y axis from its `centre`
+//(the middle of a disk or torus, the middle of the base of a cylinder or cone).
+
+use std::f64::consts::PI;
+
+use crate::{
+    aabb::Aabb,
+    material::{Colour, Material},
+    ray::{HitRecord, Hittable, Ray},
+    Point, Vec3,
+};
+
+//a flat ring facing +y; an inner radius of zero makes a full disk
+#[derive(Clone)]
+pub struct Disk {
+    centre: Point,
+    radius: f64,
+    inner_radius: f64,
+    material: Material,
+}
+impl Disk {
+    pub fn new(centre: Point, radius: f64, material: Material) -> Self {
+        Self {
+            centre,
+            radius,
+            inner_radius: 0.0,
+            material,
+        }
+    }
+
+    pub fn with_hole(mut self, inner_radius: f64) -> Self {
+        self.inner_radius = inner_radius;
+        self
+    }
+}
+impl Hittable for Disk {
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let o = r.origin - self.centre;
+        let (t, outward, uv, tangent) = hit_cap(
+            o,
+            r.direction,
+            0.0,
+            self.radius,
+            self.inner_radius,
+            1.0,
+            t_min,
+            t_max,
+        )?;
+        Some(record(r, t, outward, &self.material, uv, tangent))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let extent = Vec3::new(self.radius, 0.0, self.radius);
+        Some(Aabb::new(self.centre - extent, self.centre + extent))
+    }
+}
+
+//a tube of the given radius rising `height` from its centre, optionally closed at both ends
+#[derive(Clone)]
+pub struct Cylinder {
+    centre: Point,
+    radius: f64,
+    height: f64,
+    capped: bool,
+    material: Material,
+}
+impl Cylinder {
+    pub fn new(centre: Point, radius: f64, height: f64, capped: bool, material: Material) -> Self {
+        Self {
+            centre,
+            radius,
+            height,
+            capped,
+            material,
+        }
+    }
+}
+impl Hittable for Cylinder {
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let o = r.origin - self.centre;
+        let d = r.direction;
+        let mut closest = None;
+        let mut closest_t = t_max;
+
+        //the side: x^2 + z^2 = radius^2 between the caps
+        let a = d.e[0] * d.e[0] + d.e[2] * d.e[2];
+        let half_b = o.e[0] * d.e[0] + o.e[2] * d.e[2];
+        let c = o.e[0] * o.e[0] + o.e[2] * o.e[2] - self.radius * self.radius;
+        for t in quadratic_roots(a, half_b, c) {
+            let y = o.e[1] + t * d.e[1];
+            if t < t_min || t > closest_t || y < 0.0 || y > self.height {
+                continue;
+            }
+            let p = o + t * d;
+            let outward = Vec3::new(p.e[0], 0.0, p.e[2]) / self.radius;
+            let (u, tangent) = azimuth(p);
+            closest_t = t;
+            closest = Some((t, outward, (u, y / self.height), tangent));
+            break;
+        }
+
+        if self.capped {
+            for (y, facing) in [(0.0, -1.0), (self.height, 1.0)] {
+                if let Some(cap) = hit_cap(o, d, y, self.radius, 0.0, facing, t_min, closest_t) {
+                    closest_t = cap.0;
+                    closest = Some(cap);
+                }
+            }
+        }
+
+        let (t, outward, uv, tangent) = closest?;
+        Some(record(r, t, outward, &self.material, uv, tangent))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::new(
+            self.centre - Vec3::new(self.radius, 0.0, self.radius),
+            self.centre + Vec3::new(self.radius, self.height, self.radius),
+        ))
+    }
+}
+
+//narrows from a base of the given radius to a point `height` above its centre, optionally with its base closed
+#[derive(Clone)]
+pub struct Cone {
+    centre: Point,
+    radius: f64,
+    height: f64,
+    capped: bool,
+    material: Material,
+}
+impl Cone {
+    pub fn new(centre: Point, radius: f64, height: f64, capped: bool, material: Material) -> Self {
+        Self {
+            centre,
+            radius,
+            height,
+            capped,
+            material,
+        }
+    }
+}
+impl Hittable for Cone {
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let o = r.origin - self.centre;
+        let d = r.direction;
+        let mut closest = None;
+        let mut closest_t = t_max;
+
+        //the side: x^2 + z^2 = (k * (height - y))^2 below the apex
+        let k2 = (self.radius / self.height).powi(2);
+        let below_apex = self.height - o.e[1];
+        let a = d.e[0] * d.e[0] + d.e[2] * d.e[2] - k2 * d.e[1] * d.e[1];
+        let half_b = o.e[0] * d.e[0] + o.e[2] * d.e[2] + k2 * below_apex * d.e[1];
+        let c = o.e[0] * o.e[0] + o.e[2] * o.e[2] - k2 * below_apex * below_apex;
+        for t in quadratic_roots(a, half_b, c) {
+            let y = o.e[1] + t * d.e[1];
+            if t < t_min || t > closest_t || y < 0.0 || y > self.height {
+                continue;
+            }
+            let p = o + t * d;
+            let gradient = Vec3::new(p.e[0], k2 * (self.height - y), p.e[2]);
+            let outward = match gradient.is_near_zero() {
+                true => Vec3::new(0.0, 1.0, 0.0), //the apex
+                false => gradient.unit(),
+            };
+            let (u, tangent) = azimuth(p);
+            closest_t = t;
+            closest = Some((t, outward, (u, y / self.height), tangent));
+            break;
+        }
+
+        if self.capped {
+            if let Some(cap) = hit_cap(o, d, 0.0, self.radius, 0.0, -1.0, t_min, closest_t) {
+                closest = Some(cap);
+            }
+        }
+
+        let (t, outward, uv, tangent) = closest?;
+        Some(record(r, t, outward, &self.material, uv, tangent))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::new(
+            self.centre - Vec3::new(self.radius, 0.0, self.radius),
+            self.centre + Vec3::new(self.radius, self.height, self.radius),
+        ))
+    }
+}
+
+//a ring of tube around the y axis: `major_radius` to the middle of the tube, `minor_radius` across it
+#[derive(Clone)]
+pub struct Torus {
+    centre: Point,
+    major_radius: f64,
+    minor_radius: f64,
+    material: Material,
+}
+impl Torus {
+    pub fn new(centre: Point, major_radius: f64, minor_radius: f64, material: Material) -> Self {
+        Self {
+            centre,
+            major_radius,
+            minor_radius,
+            material,
+        }
+    }
+}
+impl Hittable for Torus {
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        //the quartic is badly conditioned far from the torus, so solve it from where the ray
+        //enters the bounding box, along a unit direction
+        let (entry, _) = self.bounding_box()?.intersect(r, t_min, t_max)?;
+        let length = r.direction.length();
+        let d = r.direction / length;
+        let o = r.at(entry) - self.centre;
+
+        //(|p|^2 + R^2 - r^2)^2 = 4R^2(x^2 + z^2), with p = o + s * d
+        let major2 = self.major_radius * self.major_radius;
+        let beta = 2.0 * Vec3::dot(o, d);
+        let gamma = o.length_squared() + major2 - self.minor_radius * self.minor_radius;
+        let coefficients = [
+            2.0 * beta,
+            beta * beta + 2.0 * gamma - 4.0 * major2 * (d.e[0] * d.e[0] + d.e[2] * d.e[2]),
+            2.0 * beta * gamma - 8.0 * major2 * (o.e[0] * d.e[0] + o.e[2] * d.e[2]),
+            gamma * gamma - 4.0 * major2 * (o.e[0] * o.e[0] + o.e[2] * o.e[2]),
+        ];
+
+        let (s, t) = solve_quartic(coefficients)
+            .into_iter()
+            .map(|s| (s, entry + s / length))
+            .filter(|&(_, t)| t >= t_min && t <= t_max)
+            .min_by(|a, b| a.1.total_cmp(&b.1))?;
+
+        let p = o + s * d;
+        let ring = Vec3::new(p.e[0], 0.0, p.e[2]);
+        let ring_distance = ring.length();
+        //the direction from the middle of the tube
+        let outward = match ring_distance > 0.0 {
+            true => (p - self.major_radius * ring / ring_distance).unit(),
+            false => Vec3::new(0.0, p.e[1].signum(), 0.0),
+        };
+        let (u, tangent) = azimuth(p);
+        let v = (p.e[1].atan2(ring_distance - self.major_radius) + PI) / (2.0 * PI);
+        Some(record(r, t, outward, &self.material, (u, v), tangent))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let outer = self.major_radius + self.minor_radius;
+        let extent = Vec3::new(outer, self.minor_radius, outer);
+        Some(Aabb::new(self.centre - extent, self.centre + extent))
+    }
+}
+
+type ShapeHit = (f64, Vec3, (f64, f64), Vec3); //t, outward normal, uv, tangent
+
+//where a ray from the local origin o crosses a horizontal disk or ring at height y, facing +y or -y
+#[allow(clippy::too_many_arguments)]
+fn hit_cap(
+    o: Point,
+    d: Vec3,
+    y: f64,
+    radius: f64,
+    inner_radius: f64,
+    facing: f64,
+    t_min: f64,
+    t_max: f64,
+) -> Option<ShapeHit> {
+    if d.e[1] == 0.0 {
+        return None;
+    }
+    let t = (y - o.e[1]) / d.e[1];
+    if t < t_min || t > t_max {
+        return None;
+    }
+
+    let p = o + t * d;
+    let distance = (p.e[0] * p.e[0] + p.e[2] * p.e[2]).sqrt();
+    if distance > radius || distance < inner_radius {
+        return None;
+    }
+    let (u, tangent) = azimuth(p);
+    let v = (distance - inner_radius) / (radius - inner_radius);
+    Some((t, Vec3::new(0.0, facing, 0.0), (u, v), tangent))
+}
+
+//the fraction of a turn around the y axis, matching the sphere's u, and the direction in which it increases
+fn azimuth(p: Point) -> (f64, Vec3) {
+    let u = ((-p.e[2]).atan2(p.e[0]) + PI) / (2.0 * PI);
+    let tangent = Vec3::new(p.e[2], 0.0, -p.e[0]);
+    match tangent.is_near_zero() {
+        true => (u, Vec3::new(1.0, 0.0, 0.0)), //on the axis itself
+        false => (u, tangent.unit()),
+    }
+}
+
+fn record<'a>(
+    r: Ray,
+    t: f64,
+    outward: Vec3,
+    material: &'a Material,
+    (u, v): (f64, f64),
+    tangent: Vec3,
+) -> HitRecord<'a> {
+    let front_face = HitRecord::front_face(r, outward);
+    let normal = if front_face { outward } else { -outward };
+    //keep the tangent frame perpendicular to the normal
+    let tangent = tangent - Vec3::dot(tangent, outward) * outward;
+    let (tangent, bitangent) = match tangent.is_near_zero() {
+        true => outward.basis(),
+        false => (tangent.unit(), Vec3::cross(outward, tangent.unit())),
+    };
+
+    HitRecord {
+        t,
+        p: r.at(t),
+        normal,
+        geometric_normal: normal,
+        material,
+        front_face,
+        emitted: Colour::default(),
+        u,
+        v,
+        tangent,
+        bitangent,
+        vertex_colour: None,
+        undetailed_normal: None,
+        object_id: None,
+    }
+    .with_detail()
+}
+
+//real roots of a*t^2 + 2*half_b*t + c, nearest first
+fn quadratic_roots(a: f64, half_b: f64, c: f64) -> Vec<f64> {
+    if a.abs() < 1e-12 {
+        //degenerate into a line, e.g. a ray parallel to a cone's side
+        return match half_b == 0.0 {
+            true => vec![],
+            false => vec![-c / (2.0 * half_b)],
+        };
+    }
+    let discriminant = half_b * half_b - a * c;
+    if discriminant < 0.0 {
+        return vec![];
+    }
+    let sqrtd = discriminant.sqrt();
+    let (t0, t1) = ((-half_b - sqrtd) / a, (-half_b + sqrtd) / a);
+    if t0 <= t1 {
+        vec![t0, t1]
+    } else {
+        vec![t1, t0]
+    }
+}
+
+//the largest real root of t^3 + a*t^2 + b*t + c
+fn largest_cubic_root(a: f64, b: f64, c: f64) -> f64 {
+    //substitute t = z - a/3 to get z^3 + p*z + q
+    let p = b - a * a / 3.0;
+    let q = 2.0 * a * a * a / 27.0 - a * b / 3.0 + c;
+    let discriminant = (q / 2.0).powi(2) + (p / 3.0).powi(3);
+
+    let z = if discriminant > 0.0 {
+        //one real root, by Cardano
+        let sqrtd = discriminant.sqrt();
+        (-q / 2.0 + sqrtd).cbrt() + (-q / 2.0 - sqrtd).cbrt()
+    } else if p == 0.0 {
+        0.0
+    } else {
+        //three real roots, by Viète's trigonometric method; k = 0 gives the largest
+        let m = 2.0 * (-p / 3.0).sqrt();
+        let theta = ((3.0 * q / (p * m)).clamp(-1.0, 1.0)).acos() / 3.0;
+        m * theta.cos()
+    };
+    z - a / 3.0
+}
+
+//real roots of t^4 + a*t^3 + b*t^2 + c*t + d, by Ferrari's method with each root polished by Newton's
+fn solve_quartic([a, b, c, d]: [f64; 4]) -> Vec<f64> {
+    //substitute t = y - a/4 to get y^4 + p*y^2 + q*y + r
+    let p = b - 3.0 * a * a / 8.0;
+    let q = c - a * b / 2.0 + a * a * a / 8.0;
+    let r = d - a * c / 4.0 + a * a * b / 16.0 - 3.0 * a.powi(4) / 256.0;
+
+    let mut roots = vec![];
+    if q.abs() < 1e-12 {
+        //biquadratic in y^2
+        for z in quadratic_roots(1.0, p / 2.0, r) {
+            if z >= 0.0 {
+                roots.extend([z.sqrt(), -z.sqrt()]);
+            }
+        }
+    } else {
+        //completing the square needs a positive root m of the resolvent cubic
+        let m = largest_cubic_root(p, p * p / 4.0 - r, -q * q / 8.0);
+        if m <= 0.0 {
+            return vec![];
+        }
+        let s = (2.0 * m).sqrt();
+        roots.extend(quadratic_roots(1.0, -s / 2.0, p / 2.0 + m + q / (2.0 * s)));
+        roots.extend(quadratic_roots(1.0, s / 2.0, p / 2.0 + m - q / (2.0 * s)));
+    }
+
+    roots
+        .into_iter()
+        .map(|y| {
+            let mut t = y - a / 4.0;
+            for _ in 0..2 {
+                let value = (((t + a) * t + b) * t + c) * t + d;
+                let slope = ((4.0 * t + 3.0 * a) * t + 2.0 * b) * t + c;
+                if slope != 0.0 {
+                    t -= value / slope;
+                }
+            }
+            t
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    fn close_vec(a: Vec3, b: Vec3) -> bool {
+        (a - b).length() < 1e-6
+    }
+
+    fn ray(origin: [f64; 3], direction: [f64; 3]) -> Ray {
+        let [x, y, z] = origin;
+        let [dx, dy, dz] = direction;
+        Ray::new(Point::new(x, y, z), Vec3::new(dx, dy, dz))
+    }
+
+    //asserts a hit at t with this normal, as the record gives it facing the ray, on the front face or not
+    fn assert_hit(shape: &dyn Hittable, r: Ray, t: f64, normal: [f64; 3], front_face: bool) {
+        let rec = shape
+            .hit(r, 0.0001, f64::INFINITY)
+            .expect("the ray should hit");
+        let [x, y, z] = normal;
+        let normal = Vec3::new(x, y, z).unit();
+        assert!(close(rec.t, t), "t {} rather than {}", rec.t, t);
+        assert!(close_vec(rec.p, r.at(t)));
+        assert!(
+            close_vec(rec.normal, normal),
+            "normal {:?} rather than {:?}",
+            rec.normal.e,
+            normal.e
+        );
+        assert_eq!(rec.front_face, front_face);
+    }
+
+    fn assert_miss(shape: &dyn Hittable, r: Ray) {
+        assert!(shape.hit(r, 0.0001, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn disk() {
+        let disk = Disk::new(Point::default(), 1.0, Material::default());
+        assert_hit(
+            &disk,
+            ray([0.5, 1.0, 0.0], [0.0, -1.0, 0.0]),
+            1.0,
+            [0.0, 1.0, 0.0],
+            true,
+        );
+        assert_hit(
+            &disk,
+            ray([0.0, -2.0, 0.0], [0.0, 1.0, 0.0]),
+            2.0,
+            [0.0, -1.0, 0.0],
+            false,
+        );
+        assert_hit(
+            &disk,
+            ray([-1.0, 1.0, 0.0], [1.0, -1.0, 0.0]),
+            1.0,
+            [0.0, 1.0, 0.0],
+            true,
+        );
+        assert_miss(&disk, ray([1.5, 1.0, 0.0], [0.0, -1.0, 0.0]));
+        assert_miss(&disk, ray([0.0, 1.0, 0.0], [1.0, 0.0, 0.0]));
+    }
+
+    #[test]
+    fn ring() {
+        let ring = Disk::new(Point::default(), 1.0, Material::default()).with_hole(0.5);
+        assert_hit(
+            &ring,
+            ray([0.75, 1.0, 0.0], [0.0, -1.0, 0.0]),
+            1.0,
+            [0.0, 1.0, 0.0],
+            true,
+        );
+        assert_miss(&ring, ray([0.25, 1.0, 0.0], [0.0, -1.0, 0.0]));
+    }
+
+    #[test]
+    fn cylinder() {
+        let cylinder = Cylinder::new(Point::default(), 1.0, 2.0, true, Material::default());
+        //the side, from outside, from inside, and at an angle
+        assert_hit(
+            &cylinder,
+            ray([-3.0, 1.0, 0.0], [1.0, 0.0, 0.0]),
+            2.0,
+            [-1.0, 0.0, 0.0],
+            true,
+        );
+        assert_hit(
+            &cylinder,
+            ray([0.0, 1.0, 0.0], [1.0, 0.0, 0.0]),
+            1.0,
+            [-1.0, 0.0, 0.0],
+            false,
+        );
+        assert_hit(
+            &cylinder,
+            ray([-2.0, 0.0, 0.0], [1.0, 1.0, 0.0]),
+            1.0,
+            [-1.0, 0.0, 0.0],
+            true,
+        );
+        //the caps
+        assert_hit(
+            &cylinder,
+            ray([0.5, 5.0, 0.0], [0.0, -1.0, 0.0]),
+            3.0,
+            [0.0, 1.0, 0.0],
+            true,
+        );
+        assert_hit(
+            &cylinder,
+            ray([0.0, -1.0, 0.0], [0.0, 1.0, 0.0]),
+            1.0,
+            [0.0, -1.0, 0.0],
+            true,
+        );
+        //passing above, and down an open tube
+        assert_miss(&cylinder, ray([-3.0, 3.0, 0.0], [1.0, 0.0, 0.0]));
+        let tube = Cylinder::new(Point::default(), 1.0, 2.0, false, Material::default());
+        assert_miss(&tube, ray([0.0, 5.0, 0.0], [0.0, -1.0, 0.0]));
+    }
+
+    #[test]
+    fn cone() {
+        let cone = Cone::new(Point::default(), 1.0, 1.0, true, Material::default());
+        let slope = [-1.0, 1.0, 0.0];
+        assert_hit(
+            &cone,
+            ray([-2.0, 0.5, 0.0], [1.0, 0.0, 0.0]),
+            1.5,
+            slope,
+            true,
+        );
+        assert_hit(
+            &cone,
+            ray([-1.0, 1.5, 0.0], [1.0, -1.0, 0.0]),
+            0.75,
+            slope,
+            true,
+        );
+        assert_hit(
+            &cone,
+            ray([0.2, -1.0, 0.0], [0.0, 1.0, 0.0]),
+            1.0,
+            [0.0, -1.0, 0.0],
+            true,
+        );
+        //the other nappe of the double cone the equation describes isn't part of it
+        assert_miss(&cone, ray([-2.0, 2.0, 0.0], [1.0, 0.0, 0.0]));
+        let open = Cone::new(Point::default(), 1.0, 1.0, false, Material::default());
+        assert_hit(
+            &open,
+            ray([0.2, -1.0, 0.0], [0.0, 1.0, 0.0]),
+            1.8,
+            [-1.0, -1.0, 0.0],
+            false,
+        );
+    }
+
+    #[test]
+    fn torus() {
+        let torus = Torus::new(Point::default(), 2.0, 0.5, Material::default());
+        assert_hit(
+            &torus,
+            ray([-5.0, 0.0, 0.0], [1.0, 0.0, 0.0]),
+            2.5,
+            [-1.0, 0.0, 0.0],
+            true,
+        );
+        assert_hit(
+            &torus,
+            ray([2.0, 3.0, 0.0], [0.0, -1.0, 0.0]),
+            2.5,
+            [0.0, 1.0, 0.0],
+            true,
+        );
+        assert_hit(
+            &torus,
+            ray([1.0, 1.5, 1.0], [1.0, -1.0, -1.0]),
+            1.0,
+            [0.0, 1.0, 0.0],
+            true,
+        );
+        //far away, where the quartic is worst conditioned
+        assert_hit(
+            &torus,
+            ray([-1000.0, 0.0, 0.0], [1.0, 0.0, 0.0]),
+            997.5,
+            [-1.0, 0.0, 0.0],
+            true,
+        );
+        //from inside the tube
+        assert_hit(
+            &torus,
+            ray([2.0, 0.0, 0.0], [1.0, 0.0, 0.0]),
+            0.5,
+            [-1.0, 0.0, 0.0],
+            false,
+        );
+        //from the hole in the middle, outwards, and straight down through it
+        assert_hit(
+            &torus,
+            ray([0.0, 0.0, 0.0], [1.0, 0.0, 0.0]),
+            1.5,
+            [-1.0, 0.0, 0.0],
+            true,
+        );
+        assert_miss(&torus, ray([0.0, 3.0, 0.0], [0.0, -1.0, 0.0]));
+        assert_miss(&torus, ray([-5.0, 1.0, 0.0], [1.0, 0.0, 0.0]));
+    }
+
+    fn assert_roots(coefficients: [f64; 4], expected: &[f64]) {
+        let mut roots = solve_quartic(coefficients);
+        roots.sort_by(f64::total_cmp);
+        assert_eq!(roots.len(), expected.len(), "roots {:?}", roots);
+        for (root, expected) in roots.iter().zip(expected) {
+            assert!((root - expected).abs() < 1e-6, "roots {:?}", roots);
+        }
+    }
+
+    #[test]
+    fn quartic_distinct_roots() {
+        //(t - 1)(t - 2)(t - 3)(t - 4)
+        assert_roots([-10.0, 35.0, -50.0, 24.0], &[1.0, 2.0, 3.0, 4.0]);
+        //(t - 1)(t + 3)(t^2 + 1)
+        assert_roots([2.0, -2.0, 2.0, -3.0], &[-3.0, 1.0]);
+        //(t^2 - 1)(t^2 - 4), which has no cubic term once depressed
+        assert_roots([0.0, -5.0, 0.0, 4.0], &[-2.0, -1.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn quartic_repeated_roots() {
+        //(t - 1)^2 (t + 2)^2
+        assert_roots([2.0, -3.0, -4.0, 4.0], &[-2.0, -2.0, 1.0, 1.0]);
+        //(t - 2)^2 (t^2 + 1)
+        assert_roots([-4.0, 5.0, -4.0, 4.0], &[2.0, 2.0]);
+    }
+
+    #[test]
+    fn quartic_no_real_roots() {
+        assert_roots([0.0, 0.0, 0.0, 1.0], &[]);
+        //(t^2 + 2t + 2)(t^2 - 2t + 5)
+        assert_roots([0.0, 3.0, 6.0, 10.0], &[]);
+    }
+}
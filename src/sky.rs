@@ -0,0 +1,276 @@
+//daylight: a clear sky and the sun, placed by hand or from a date, time and place
+
+use std::f64::consts::PI;
+
+use rand::Rng;
+
+use crate::{
+    material::{spectrum_to_xyz, xyz_to_rgb, Colour},
+    Vec3,
+};
+
+//the sun's angular radius as seen from the earth, in radians
+const SUN_RADIUS: f64 = 0.2665 * PI / 180.0;
+
+//Preetham, Shirley and Smits' analytic model of a clear sky (1999), lit by the sun's disk, whose light is
+//dimmed and reddened on its way through the air. Luminances are in cd/m², as `Camera::with_exposure` expects,
+//so a sunny scene wants something like ISO 100, 1/125s at f/16. Below the horizon is an endless ground lit by
+//the sky and sun; after sunset everything is dark.
+#[derive(Clone, Debug)]
+pub struct Sky {
+    sun: Vec3, //towards the middle of the disk
+    sun_zenith: f64,
+    perez: [[f64; 5]; 3], //the distribution coefficients for luminance and the x and y chromaticities
+    zenith: [f64; 3],     //the same three straight up, each divided by its distribution there
+    sun_radiance: Colour,
+    irradiance: Colour, //falling on the ground from the sky and sun together
+    ground: Colour,
+}
+impl Sky {
+    //the sun `elevation` degrees above the horizon, `azimuth` degrees clockwise from north (-z) towards
+    //east (+x). Turbidity is how hazy the air is, from 2 for a clear mountain day to 10 for a murky one
+    pub fn new(elevation: f64, azimuth: f64, turbidity: f64) -> Self {
+        let (elevation, azimuth) = (elevation.to_radians(), azimuth.to_radians());
+        let turbidity = turbidity.clamp(1.7, 10.0); //beyond this the fit breaks down
+        let sun = Vec3::new(
+            elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+            -elevation.cos() * azimuth.cos(),
+        );
+        let theta = (PI / 2.0 - elevation).clamp(0.0, PI / 2.0);
+        let t = turbidity;
+
+        let perez = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+
+        //the zenith's luminance, in kcd/m², and chromaticity
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta);
+        let luminance = 1000.0 * ((4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192);
+        let chromaticity = |m: [[f64; 4]; 3]| {
+            let powers = [theta.powi(3), theta.powi(2), theta, 1.0];
+            [t * t, t, 1.0]
+                .iter()
+                .zip(&m)
+                .map(|(tp, row)| tp * row.iter().zip(&powers).map(|(a, b)| a * b).sum::<f64>())
+                .sum::<f64>()
+        };
+        let x = chromaticity([
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let y = chromaticity([
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+        let mut zenith = [luminance, x, y];
+        for (value, coefficients) in zenith.iter_mut().zip(&perez) {
+            *value /= distribution(coefficients, 1.0, theta);
+        }
+
+        let mut sky = Self {
+            sun,
+            sun_zenith: theta,
+            perez,
+            zenith,
+            sun_radiance: sun_radiance(theta, turbidity),
+            irradiance: Colour::default(),
+            ground: Colour::default(),
+        };
+        if sun.e[1] > 0.0 {
+            sky.irradiance = sky.ground_irradiance();
+        }
+        sky.with_ground_albedo(Colour::new(0.2, 0.2, 0.2))
+    }
+
+    //how much light the ground below the horizon reflects
+    pub fn with_ground_albedo(mut self, albedo: Colour) -> Self {
+        self.ground = albedo * self.irradiance / PI;
+        self
+    }
+
+    //the unit direction towards the sun
+    pub fn sun_direction(&self) -> Vec3 {
+        self.sun
+    }
+
+    //the light from the sky or ground along a direction, leaving out the sun's disk
+    pub fn radiance(&self, direction: Vec3) -> Colour {
+        if self.sun.e[1] <= 0.0 {
+            return Colour::default();
+        }
+        let direction = direction.unit();
+        if direction.e[1] < 0.0 {
+            return self.ground;
+        }
+        let cos_gamma = Vec3::dot(direction, self.sun).clamp(-1.0, 1.0);
+        let [luminance, x, y] = [0, 1, 2].map(|i| {
+            self.zenith[i] * distribution(&self.perez[i], direction.e[1], cos_gamma.acos())
+        });
+        xyz_to_rgb(Vec3::new(
+            x / y * luminance,
+            luminance,
+            (1.0 - x - y) / y * luminance,
+        ))
+    }
+
+    //the sun's own light along a direction: all of it within its disk, none outside
+    pub fn sun_radiance(&self, direction: Vec3) -> Colour {
+        let inside = Vec3::dot(direction.unit(), self.sun) >= SUN_RADIUS.cos();
+        match inside && self.sun.e[1] > 0.0 {
+            true => self.sun_radiance,
+            false => Colour::default(),
+        }
+    }
+
+    //a direction picked evenly across the sun's disk, its light, and how densely such directions are picked;
+    //None when the sun has set
+    pub(crate) fn sample_sun(&self) -> Option<(Vec3, Colour, f64)> {
+        if self.sun.e[1] <= 0.0 {
+            return None;
+        }
+        let mut rng = rand::thread_rng();
+        let cos_theta = 1.0 - rng.gen::<f64>() * (1.0 - SUN_RADIUS.cos());
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * rng.gen::<f64>();
+        let (tangent, bitangent) = self.sun.basis();
+        let direction = sin_theta * phi.cos() * tangent
+            + sin_theta * phi.sin() * bitangent
+            + cos_theta * self.sun;
+        Some((direction, self.sun_radiance, self.sun_pdf()))
+    }
+
+    //the density `sample_sun` picks directions with, per steradian
+    pub(crate) fn sun_pdf(&self) -> f64 {
+        1.0 / sun_solid_angle()
+    }
+
+    //the light landing on flat ground from the whole sky, summed numerically, and from the sun
+    fn ground_irradiance(&self) -> Colour {
+        const STEPS: usize = 64;
+        let mut irradiance = Colour::default();
+        for i in 0..STEPS {
+            let theta = (i as f64 + 0.5) / STEPS as f64 * PI / 2.0;
+            for j in 0..2 * STEPS {
+                let phi = (j as f64 + 0.5) / STEPS as f64 * PI;
+                let direction = Vec3::new(
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin(),
+                );
+                //cosine-weighted, over cells (pi / 2 / steps) by (pi / steps) wide
+                irradiance = irradiance
+                    + theta.cos()
+                        * theta.sin()
+                        * self.radiance(direction)
+                        * (PI / STEPS as f64)
+                        * (PI / 2.0 / STEPS as f64);
+            }
+        }
+        irradiance + self.sun_radiance * sun_solid_angle() * self.sun_zenith.cos()
+    }
+}
+
+//the Perez distribution's value a zenith angle and an angle from the sun away, as Preetham fits it
+fn distribution(coefficients: &[f64; 5], cos_theta: f64, gamma: f64) -> f64 {
+    let [a, b, c, d, e] = *coefficients;
+    (1.0 + a * (b / cos_theta.max(1e-3)).exp())
+        * (1.0 + c * (d * gamma).exp() + e * gamma.cos().powi(2))
+}
+
+fn sun_solid_angle() -> f64 {
+    2.0 * PI * (1.0 - SUN_RADIUS.cos())
+}
+
+//sunlight through as much air as lies between the ground and the sun `theta` from straight up
+fn sun_radiance(theta: f64, turbidity: f64) -> Colour {
+    //the sun as a 5778K black body, per nanometre
+    let planck =
+        |lambda: f64| (lambda * 1e-9).powi(-5) / ((1.4388e7 / (lambda * 5778.0)).exp() - 1.0);
+    //Kasten and Young's air mass, relative to straight up
+    let air_mass = 1.0 / (theta.cos() + 0.50572 * (96.07995 - theta.to_degrees()).powf(-1.6364));
+    //Ångström's haze coefficient for the turbidity, as Preetham gives it
+    let beta = 0.04608 * turbidity - 0.04586;
+    let transmittance = |lambda: f64| {
+        let microns = lambda / 1000.0;
+        let rayleigh = 0.008735 * microns.powf(-4.08);
+        let aerosol = beta * microns.powf(-1.3);
+        (-(rayleigh + aerosol) * air_mass).exp()
+    };
+
+    //about 128,000 lux arrives above the atmosphere, from across the whole disk
+    let outside = spectrum_to_xyz(planck).e[1];
+    let through = spectrum_to_xyz(|lambda| planck(lambda) * transmittance(lambda));
+    xyz_to_rgb(through * (128_000.0 / sun_solid_angle() / outside))
+}
+
+//where the sun is in the sky, as `Sky::new` takes it: degrees of elevation and of azimuth clockwise from
+//north. The time is in hours UTC on the given day, and longitude is east of Greenwich; this follows NOAA's
+//approximation, good to a few hundredths of a degree, and ignores refraction near the horizon.
+pub fn sun_position(
+    year: i32,
+    month: u32,
+    day: u32,
+    hour: f64,
+    latitude: f64,
+    longitude: f64,
+) -> (f64, f64) {
+    let leap = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+    const BEFORE: [u32; 12] = [0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334];
+    let month = month.clamp(1, 12);
+    let day_of_year = BEFORE[month as usize - 1] + day + (leap && month > 2) as u32;
+    let days = if leap { 366.0 } else { 365.0 };
+
+    //the fraction of the year gone, in radians
+    let g = 2.0 * PI / days * (day_of_year as f64 - 1.0 + (hour - 12.0) / 24.0);
+    let equation_of_time = 229.18
+        * (0.000075 + 0.001868 * g.cos()
+            - 0.032077 * g.sin()
+            - 0.014615 * (2.0 * g).cos()
+            - 0.040849 * (2.0 * g).sin());
+    let declination = 0.006918 - 0.399912 * g.cos() + 0.070257 * g.sin()
+        - 0.006758 * (2.0 * g).cos()
+        + 0.000907 * (2.0 * g).sin()
+        - 0.002697 * (3.0 * g).cos()
+        + 0.00148 * (3.0 * g).sin();
+
+    //true solar time, in minutes, gives the hour angle: zero at local noon and growing westwards
+    let solar_time = hour * 60.0 + equation_of_time + 4.0 * longitude;
+    let hour_angle = (solar_time / 4.0 - 180.0).to_radians();
+    let latitude = latitude.to_radians();
+
+    let elevation = (latitude.sin() * declination.sin()
+        + latitude.cos() * declination.cos() * hour_angle.cos())
+    .clamp(-1.0, 1.0)
+    .asin();
+    let azimuth = hour_angle
+        .sin()
+        .atan2(hour_angle.cos() * latitude.sin() - declination.tan() * latitude.cos())
+        + PI;
+    (
+        elevation.to_degrees(),
+        azimuth.to_degrees().rem_euclid(360.0),
+    )
+}
@@ -0,0 +1,149 @@
+//stereolithography: a soup of unconnected triangles, as an ascii list of facets or packed binary records
+
+use std::collections::HashMap;
+
+use crate::{
+    material::Material,
+    mesh::{MeshError, TriangleMesh},
+    Point,
+};
+
+pub(crate) fn parse(bytes: &[u8], material: Material) -> Result<TriangleMesh, MeshError> {
+    //ascii files start with "solid", but so do some binary ones, so trust the binary size if it fits exactly
+    let triangles = match bytes.get(80..84) {
+        Some(&[a, b, c, d])
+            if 84 + 50 * u32::from_le_bytes([a, b, c, d]) as usize == bytes.len() =>
+        {
+            binary(bytes)
+        }
+        _ => match std::str::from_utf8(bytes) {
+            Ok(text) if text.starts_with("solid") => ascii(text)?,
+            _ => return Err(MeshError::Truncated), //binary, but shorter or longer than its count says
+        },
+    };
+
+    //facets repeat their shared corners, so join identical ones back up
+    let mut positions = vec![];
+    let mut indices = vec![];
+    let mut seen = HashMap::new();
+    for triangle in triangles {
+        indices.push(triangle.map(|p| {
+            let key = p.e.map(f64::to_bits);
+            *seen.entry(key).or_insert_with(|| {
+                positions.push(p);
+                positions.len() - 1
+            })
+        }));
+    }
+    TriangleMesh::checked(positions, indices, material)
+}
+
+//an 80-byte header, a u32 count, then per triangle a normal, three corners and two spare bytes
+fn binary(bytes: &[u8]) -> Vec<[Point; 3]> {
+    bytes[84..]
+        .chunks_exact(50)
+        .map(|record| {
+            let float = |at: usize| {
+                f32::from_le_bytes([record[at], record[at + 1], record[at + 2], record[at + 3]])
+                    as f64
+            };
+            [12, 24, 36].map(|at| Point::new(float(at), float(at + 4), float(at + 8)))
+        })
+        .collect()
+}
+
+fn ascii(text: &str) -> Result<Vec<[Point; 3]>, MeshError> {
+    let mut triangles = vec![];
+    let mut corners = vec![];
+    for (i, line) in text.lines().enumerate() {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words[..] {
+            ["vertex", x, y, z] => {
+                let parse = |word: &str| {
+                    word.parse::<f64>().map_err(|_| {
+                        MeshError::Parse(i + 1, format!("expected a number, not {:?}", word))
+                    })
+                };
+                corners.push(Point::new(parse(x)?, parse(y)?, parse(z)?));
+            }
+            ["endloop"] => {
+                //polygons with more than three corners become fans of triangles
+                for j in 1..corners.len().saturating_sub(1) {
+                    triangles.push([corners[0], corners[j], corners[j + 1]]);
+                }
+                corners.clear();
+            }
+            ["vertex", ..] => {
+                return Err(MeshError::Parse(
+                    i + 1,
+                    "a vertex needs three coordinates".to_string(),
+                ))
+            }
+            _ => {} //solid, facet normal, outer loop, endfacet and endsolid carry nothing we need
+        }
+    }
+    if !corners.is_empty() {
+        return Err(MeshError::Truncated);
+    }
+    Ok(triangles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ray::{Hittable, Ray},
+        Vec3,
+    };
+
+    fn fixture(name: &str) -> Vec<u8> {
+        let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name);
+        std::fs::read(path).unwrap()
+    }
+
+    //the fixtures are a unit square in the xz plane, facing up, as two facets
+    fn assert_square(mesh: &TriangleMesh) {
+        let bounds = mesh.bounding_box().unwrap();
+        assert_eq!(bounds.min.e, [0.0, 0.0, 0.0]);
+        assert_eq!(bounds.max.e, [1.0, 0.0, 1.0]);
+        for (x, z) in [(0.2, 0.7), (0.7, 0.2)] {
+            let down = Ray::new(Point::new(x, 1.0, z), Vec3::new(0.0, -1.0, 0.0));
+            let rec = mesh.hit(down, 0.0001, f64::INFINITY).unwrap();
+            assert!((rec.t - 1.0).abs() < 1e-9);
+            assert!(rec.front_face);
+        }
+    }
+
+    #[test]
+    fn ascii() {
+        assert_square(&parse(&fixture("square_ascii.stl"), Material::default()).unwrap());
+    }
+
+    //its header starts with "solid", like an ascii file's
+    #[test]
+    fn binary() {
+        assert_square(&parse(&fixture("square_binary.stl"), Material::default()).unwrap());
+    }
+
+    #[test]
+    fn truncated() {
+        let binary = fixture("square_binary.stl");
+        let error = parse(&binary[..binary.len() - 10], Material::default())
+            .err()
+            .unwrap();
+        assert!(matches!(error, MeshError::Truncated));
+        let ascii = "solid cut\nfacet normal 0 1 0\nouter loop\nvertex 0 0 0\nvertex 1 0 0\n";
+        let error = parse(ascii.as_bytes(), Material::default()).err().unwrap();
+        assert!(matches!(error, MeshError::Truncated));
+    }
+
+    #[test]
+    fn bad_vertex() {
+        let ascii = "solid bad\nfacet normal 0 1 0\nouter loop\nvertex 0 0\n";
+        let error = parse(ascii.as_bytes(), Material::default()).err().unwrap();
+        assert!(matches!(error, MeshError::Parse(4, _)));
+        let ascii = "solid bad\nfacet normal 0 1 0\nouter loop\nvertex 0 x 0\n";
+        let error = parse(ascii.as_bytes(), Material::default()).err().unwrap();
+        assert!(matches!(error, MeshError::Parse(4, _)));
+    }
+}
@@ -0,0 +1,266 @@
+use std::{fs::File, io, io::Read, path::Path, sync::Arc};
+
+use rand::Rng;
+
+use crate::{material::Colour, noise::Perlin, ray::HitRecord, Vec3};
+
+//a colour that varies over a surface, looked up by texture coordinates or position
+#[derive(Clone)]
+pub enum Texture {
+    Solid(Colour),
+    Checker(Colour, Colour, f64), //alternating 3D cells of two colours; cells per unit length
+    Noise(Arc<Perlin>, f64),      //marble-like veins of turbulence; frequency
+    Image(Arc<Image>),
+    VertexColour(Colour), //a mesh's per-vertex colours; the colour of surfaces without them
+    VertexTinted(Arc<Image>), //an image multiplied by a mesh's per-vertex colours, as glTF combines them
+}
+impl Default for Texture {
+    fn default() -> Self {
+        Self::Solid(Colour::new(0.5, 0.5, 0.5))
+    }
+}
+impl From<Colour> for Texture {
+    fn from(colour: Colour) -> Self {
+        Self::Solid(colour)
+    }
+}
+impl Texture {
+    pub fn value(&self, rec: &HitRecord) -> Colour {
+        let p = rec.p;
+        match self {
+            Self::Solid(colour) => *colour,
+            Self::Checker(odd, even, frequency) => {
+                let sines = (frequency * p.e[0]).sin()
+                    * (frequency * p.e[1]).sin()
+                    * (frequency * p.e[2]).sin();
+                if sines < 0.0 {
+                    *odd
+                } else {
+                    *even
+                }
+            }
+            Self::Noise(noise, frequency) => {
+                let phase = frequency * p.e[2] + 10.0 * noise.turbulence(p, 7);
+                Colour::new(1.0, 1.0, 1.0) * 0.5 * (1.0 + phase.sin())
+            }
+            Self::Image(image) => image.sample(rec.u, rec.v),
+            Self::VertexColour(fallback) => rec.vertex_colour.unwrap_or(*fallback),
+            Self::VertexTinted(image) => {
+                let tint = rec.vertex_colour.unwrap_or(Colour::new(1.0, 1.0, 1.0));
+                tint * image.sample(rec.u, rec.v)
+            }
+        }
+    }
+}
+
+//fine surface detail that tilts the shading normal without changing the geometry
+#[derive(Clone)]
+pub enum Detail {
+    NormalMap(Arc<Image>),    //tangent-space normals, as most tools export them
+    BumpMap(Arc<Image>, f64), //heights in the red channel; height of white in uv units
+    NoiseBump(Arc<Perlin>, f64, f64), //procedural bumps; frequency and height
+}
+impl Detail {
+    //the perturbed outward normal at a hit, given its unperturbed outward normal
+    pub fn perturb(&self, rec: &HitRecord, normal: Vec3) -> Vec3 {
+        let perturbed = match *self {
+            Self::NormalMap(ref image) => {
+                let encoded = image.sample(rec.u, rec.v);
+                let local = 2.0 * encoded - Vec3::new(1.0, 1.0, 1.0);
+                local.e[0] * rec.tangent + local.e[1] * rec.bitangent + local.e[2] * normal
+            }
+            Self::BumpMap(ref image, height) => {
+                //finite differences of the height one texel along u and v
+                let (du, dv) = (1.0 / image.width() as f64, 1.0 / image.height() as f64);
+                let centre = image.sample(rec.u, rec.v).e[0];
+                let slope_u = (image.sample(rec.u + du, rec.v).e[0] - centre) / du;
+                let slope_v = (image.sample(rec.u, rec.v + dv).e[0] - centre) / dv;
+                normal - height * (slope_u * rec.tangent + slope_v * rec.bitangent)
+            }
+            Self::NoiseBump(ref noise, frequency, height) => {
+                let h = 1e-4;
+                let bump = |offset: Vec3| height * noise.noise(frequency * (rec.p + offset));
+                let gradient = Vec3::new(
+                    bump(Vec3::new(h, 0.0, 0.0)) - bump(Vec3::new(-h, 0.0, 0.0)),
+                    bump(Vec3::new(0.0, h, 0.0)) - bump(Vec3::new(0.0, -h, 0.0)),
+                    bump(Vec3::new(0.0, 0.0, h)) - bump(Vec3::new(0.0, 0.0, -h)),
+                ) / (2.0 * h);
+                //only the slope along the surface tilts it
+                normal - (gradient - Vec3::dot(gradient, normal) * normal)
+            }
+        };
+
+        match perturbed.is_near_zero() {
+            true => normal,
+            false => perturbed.unit(),
+        }
+    }
+}
+
+//a bitmap of linear colours, (0, 0) being the bottom left in texture coordinates
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<Colour>,
+    alpha: Option<Vec<f64>>,
+}
+impl Image {
+    pub fn new(width: usize, height: usize, pixels: Vec<Colour>) -> Self {
+        assert_eq!(
+            width * height,
+            pixels.len(),
+            "image size doesn't match its pixels"
+        );
+        Self {
+            width,
+            height,
+            pixels,
+            alpha: None,
+        }
+    }
+
+    pub fn with_alpha(mut self, alpha: Vec<f64>) -> Self {
+        assert_eq!(self.pixels.len(), alpha.len(), "one alpha value per pixel");
+        self.alpha = Some(alpha);
+        self
+    }
+
+    //loads an sRGB-encoded PNG, such as a photo or painted colour map
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::decode_png(File::open(path)?, srgb_to_linear)
+    }
+
+    //loads a PNG holding data rather than colour, such as a normal or height map
+    pub fn open_linear<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::decode_png(File::open(path)?, |c| c)
+    }
+
+    //PNG data from anywhere, such as inside a model file, with each colour channel decoded by `decode`
+    pub(crate) fn decode_png<R: Read, F: Fn(f64) -> f64>(reader: R, decode: F) -> io::Result<Self> {
+        let mut decoder = png::Decoder::new(reader);
+        //palettes and low bit depths come out as 8 bits per channel; 16-bit images keep their precision,
+        //which heightmaps in particular need to avoid terracing
+        decoder.set_transformations(png::Transformations::EXPAND);
+        let (info, mut reader) = decoder.read_info()?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut buffer)?;
+        let (colour_type, bit_depth) = reader.output_color_type();
+        let samples: Vec<f64> = match bit_depth {
+            png::BitDepth::Sixteen => buffer
+                .chunks_exact(2)
+                .map(|b| u16::from_be_bytes([b[0], b[1]]) as f64 / 65535.0)
+                .collect(),
+            _ => buffer.iter().map(|&b| b as f64 / 255.0).collect(),
+        };
+
+        let channels = colour_type.samples();
+        let pixels = samples
+            .chunks_exact(channels)
+            .map(|pixel| {
+                let channel = |i: usize| decode(pixel[i]);
+                match channels {
+                    1 | 2 => Colour::new(channel(0), channel(0), channel(0)),
+                    _ => Colour::new(channel(0), channel(1), channel(2)),
+                }
+            })
+            .collect();
+        let image = Self::new(info.width as usize, info.height as usize, pixels);
+
+        //grey+alpha and RGBA images carry their alpha last; it's always linear
+        Ok(match channels {
+            2 | 4 => image.with_alpha(
+                samples
+                    .chunks_exact(channels)
+                    .map(|pixel| pixel[channels - 1])
+                    .collect(),
+            ),
+            _ => image,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Colour {
+        self.pixels[y * self.width + x]
+    }
+
+    //fully opaque for images without an alpha channel
+    pub(crate) fn alpha(&self, x: usize, y: usize) -> f64 {
+        self.alpha
+            .as_ref()
+            .map_or(1.0, |alpha| alpha[y * self.width + x])
+    }
+
+    //bilinear filtering, repeating the image outside [0, 1]
+    pub fn sample(&self, u: f64, v: f64) -> Colour {
+        self.texels(u, v)
+            .iter()
+            .map(|&((x, y), weight)| weight * self.pixel(x, y))
+            .fold(Colour::default(), |sum, texel| sum + texel)
+    }
+
+    //filtered like `sample`; the alpha channel if there is one, or else the red channel, as in a greyscale mask
+    pub fn opacity(&self, u: f64, v: f64) -> f64 {
+        self.texels(u, v)
+            .iter()
+            .map(|&((x, y), weight)| weight * self.pixel_opacity(x, y))
+            .sum()
+    }
+
+    //one pixel's opacity, as `opacity` reads it
+    pub(crate) fn pixel_opacity(&self, x: usize, y: usize) -> f64 {
+        match &self.alpha {
+            Some(alpha) => alpha[y * self.width + x],
+            None => self.pixel(x, y).e[0],
+        }
+    }
+
+    //the four texels around a texture coordinate, with their bilinear weights
+    fn texels(&self, u: f64, v: f64) -> [((usize, usize), f64); 4] {
+        //images are stored top row first
+        let x = u.rem_euclid(1.0) * self.width as f64 - 0.5;
+        let y = (1.0 - v.rem_euclid(1.0)) * self.height as f64 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+
+        let wrap = |i: f64, n: usize| (i as i64).rem_euclid(n as i64) as usize;
+        let (x0, x1) = (wrap(x0, self.width), wrap(x0 + 1.0, self.width));
+        let (y0, y1) = (wrap(y0, self.height), wrap(y0 + 1.0, self.height));
+
+        [
+            ((x0, y0), (1.0 - tx) * (1.0 - ty)),
+            ((x1, y0), tx * (1.0 - ty)),
+            ((x0, y1), (1.0 - tx) * ty),
+            ((x1, y1), tx * ty),
+        ]
+    }
+}
+
+//which parts of a surface exist at all, for leaves, fences and decals
+#[derive(Clone)]
+pub enum Cutout {
+    Threshold(Arc<Image>, f64), //the surface is there where the mask's opacity reaches the threshold
+    Stochastic(Arc<Image>),     //the surface is there with probability of the mask's opacity
+}
+impl Cutout {
+    pub fn is_opaque(&self, u: f64, v: f64) -> bool {
+        match self {
+            Self::Threshold(mask, threshold) => mask.opacity(u, v) >= *threshold,
+            Self::Stochastic(mask) => mask.opacity(u, v) > rand::thread_rng().gen::<f64>(),
+        }
+    }
+}
+
+pub(crate) fn srgb_to_linear(c: f64) -> f64 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
@@ -0,0 +1,188 @@
+use std::ops::Mul;
+
+use crate::{Point, Vec3};
+
+//an affine transformation, as a 4x4 matrix in row-major order whose bottom row is always 0, 0, 0, 1
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    m: [[f64; 4]; 4],
+}
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+impl Transform {
+    pub fn identity() -> Self {
+        Self::from_rows([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn from_rows(m: [[f64; 4]; 4]) -> Self {
+        Self { m }
+    }
+
+    //as glTF and OpenGL store them
+    pub fn from_columns(columns: [[f64; 4]; 4]) -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (column, values) in columns.iter().enumerate() {
+            for (row, &value) in values.iter().enumerate() {
+                m[row][column] = value;
+            }
+        }
+        Self { m }
+    }
+
+    pub fn translate(offset: Vec3) -> Self {
+        Self::from_rows([
+            [1.0, 0.0, 0.0, offset.e[0]],
+            [0.0, 1.0, 0.0, offset.e[1]],
+            [0.0, 0.0, 1.0, offset.e[2]],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn scale(factors: Vec3) -> Self {
+        Self::from_rows([
+            [factors.e[0], 0.0, 0.0, 0.0],
+            [0.0, factors.e[1], 0.0, 0.0],
+            [0.0, 0.0, factors.e[2], 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    //anticlockwise looking down the axis towards the origin
+    pub fn rotate(axis: Vec3, degrees: f64) -> Self {
+        let a = axis.unit();
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let (x, y, z) = (a.e[0], a.e[1], a.e[2]);
+        let t = 1.0 - cos;
+        Self::from_rows([
+            [
+                t * x * x + cos,
+                t * x * y - sin * z,
+                t * x * z + sin * y,
+                0.0,
+            ],
+            [
+                t * x * y + sin * z,
+                t * y * y + cos,
+                t * y * z - sin * x,
+                0.0,
+            ],
+            [
+                t * x * z - sin * y,
+                t * y * z + sin * x,
+                t * z * z + cos,
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    //a unit quaternion's rotation, x, y and z before w as in glTF
+    pub fn from_quaternion([x, y, z, w]: [f64; 4]) -> Self {
+        Self::from_rows([
+            [
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - z * w),
+                2.0 * (x * z + y * w),
+                0.0,
+            ],
+            [
+                2.0 * (x * y + z * w),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - x * w),
+                0.0,
+            ],
+            [
+                2.0 * (x * z - y * w),
+                2.0 * (y * z + x * w),
+                1.0 - 2.0 * (x * x + y * y),
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn point(&self, p: Point) -> Point {
+        self.vector(p) + Vec3::new(self.m[0][3], self.m[1][3], self.m[2][3])
+    }
+
+    //directions and offsets, which translation doesn't move
+    pub fn vector(&self, v: Vec3) -> Vec3 {
+        let row = |i: usize| self.m[i][0] * v.e[0] + self.m[i][1] * v.e[1] + self.m[i][2] * v.e[2];
+        Vec3::new(row(0), row(1), row(2))
+    }
+
+    //surface normals, which must stay perpendicular to the transformed surface; the result isn't unit length
+    pub fn normal(&self, n: Vec3) -> Vec3 {
+        //the cofactor matrix is the inverse transpose scaled by the determinant, so flip for mirror images
+        let [c0, c1, c2] = self.columns();
+        let cofactor = n.e[0] * Vec3::cross(c1, c2)
+            + n.e[1] * Vec3::cross(c2, c0)
+            + n.e[2] * Vec3::cross(c0, c1);
+        match self.determinant() < 0.0 {
+            true => -cofactor,
+            false => cofactor,
+        }
+    }
+
+    //of the linear part; negative for transformations that mirror, which turns triangles inside out
+    pub fn determinant(&self) -> f64 {
+        let [c0, c1, c2] = self.columns();
+        Vec3::dot(c0, Vec3::cross(c1, c2))
+    }
+
+    //None for transformations that flatten space
+    pub fn inverse(&self) -> Option<Self> {
+        let determinant = self.determinant();
+        if determinant.abs() < 1e-12 {
+            return None;
+        }
+        //the inverse of the linear part is the transposed cofactor matrix over the determinant
+        let [c0, c1, c2] = self.columns();
+        let rows = [
+            Vec3::cross(c1, c2) / determinant,
+            Vec3::cross(c2, c0) / determinant,
+            Vec3::cross(c0, c1) / determinant,
+        ];
+        let mut m = [
+            [0.0, 0.0, 0.0, 0.0],
+            [0.0; 4],
+            [0.0; 4],
+            [0.0, 0.0, 0.0, 1.0],
+        ];
+        for (i, row) in rows.iter().enumerate() {
+            m[i][..3].copy_from_slice(&row.e);
+        }
+        let linear = Self { m };
+        let translation = linear.vector(Vec3::new(self.m[0][3], self.m[1][3], self.m[2][3]));
+        for (i, row) in m.iter_mut().take(3).enumerate() {
+            row[3] = -translation.e[i];
+        }
+        Some(Self { m })
+    }
+
+    fn columns(&self) -> [Vec3; 3] {
+        [0, 1, 2].map(|j| Vec3::new(self.m[0][j], self.m[1][j], self.m[2][j]))
+    }
+}
+//`a * b` applies b first, then a
+impl Mul for Transform {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[i][k] * rhs.m[k][j]).sum();
+            }
+        }
+        Self { m }
+    }
+}
@@ -167,6 +167,22 @@ impl Vec3 {
         }
     }
 
+    //two unit vectors perpendicular to this (unit) vector and to each other
+    pub fn basis(self) -> (Self, Self) {
+        //branchless construction from Duff et al., "Building an Orthonormal Basis, Revisited"
+        let sign = 1.0_f64.copysign(self.e[2]);
+        let a = -1.0 / (sign + self.e[2]);
+        let b = self.e[0] * self.e[1] * a;
+        (
+            Self::new(
+                1.0 + sign * self.e[0] * self.e[0] * a,
+                sign * b,
+                -sign * self.e[0],
+            ),
+            Self::new(b, sign + self.e[1] * self.e[1] * a, -self.e[1]),
+        )
+    }
+
     pub fn is_near_zero(self) -> bool {
         self.e.iter().all(|elem| elem.abs() < 1e-8)
     }
@@ -0,0 +1,90 @@
+//uncompressed YUV4MPEG2 video, which encoders such as ffmpeg and x264 read from a file or a pipe
+
+use std::io::{self, Write};
+
+pub struct Y4mWriter<W: Write> {
+    out: W,
+    width: usize,
+    height: usize,
+}
+impl<W: Write> Y4mWriter<W> {
+    //writes the stream header; fractional rates such as 29.97 are kept to three decimal places
+    pub fn new(mut out: W, width: usize, height: usize, fps: f64) -> io::Result<Self> {
+        let (numerator, denominator) = ((fps * 1000.0).round() as u64, 1000);
+        let divisor = gcd(numerator, denominator);
+        //4:2:0 with chroma between the luma samples, square pixels and no interlacing
+        writeln!(
+            out,
+            "YUV4MPEG2 W{} H{} F{}:{} Ip A1:1 C420jpeg",
+            width,
+            height,
+            numerator / divisor,
+            denominator / divisor
+        )?;
+        Ok(Self { out, width, height })
+    }
+
+    //one frame of 8-bit RGB, top row first, as `Renderer::frame` gives it
+    pub fn write_frame(&mut self, rgb: &[u8]) -> io::Result<()> {
+        assert_eq!(
+            rgb.len(),
+            self.width * self.height * 3,
+            "one RGB triple per pixel"
+        );
+        let pixel = |x: usize, y: usize| {
+            let i = (y * self.width + x) * 3;
+            [rgb[i] as f64, rgb[i + 1] as f64, rgb[i + 2] as f64]
+        };
+
+        let luma: Vec<u8> = (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .map(|(x, y)| to_yuv(pixel(x, y))[0])
+            .collect();
+
+        //each chroma sample covers a 2x2 block, or what's left of one at odd edges
+        let (chroma_width, chroma_height) = (self.width.div_ceil(2), self.height.div_ceil(2));
+        let mut cb = Vec::with_capacity(chroma_width * chroma_height);
+        let mut cr = Vec::with_capacity(chroma_width * chroma_height);
+        for y in 0..chroma_height {
+            for x in 0..chroma_width {
+                let block: Vec<_> = (2 * y..(2 * y + 2).min(self.height))
+                    .flat_map(|y| (2 * x..(2 * x + 2).min(self.width)).map(move |x| (x, y)))
+                    .map(|(x, y)| pixel(x, y))
+                    .collect();
+                let mean = |channel: usize| {
+                    block.iter().map(|rgb| rgb[channel]).sum::<f64>() / block.len() as f64
+                };
+                let [_, u, v] = to_yuv([mean(0), mean(1), mean(2)]);
+                cb.push(u);
+                cr.push(v);
+            }
+        }
+
+        self.out.write_all(b"FRAME\n")?;
+        self.out.write_all(&luma)?;
+        self.out.write_all(&cb)?;
+        self.out.write_all(&cr)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+//BT.601 in the limited range that players assume for y4m: luma 16-235, chroma 16-240
+fn to_yuv([r, g, b]: [f64; 3]) -> [u8; 3] {
+    let (r, g, b) = (r / 255.0, g / 255.0, b / 255.0);
+    [
+        16.0 + 65.481 * r + 128.553 * g + 24.966 * b,
+        128.0 - 37.797 * r - 74.203 * g + 112.0 * b,
+        128.0 + 112.0 * r - 93.786 * g - 18.214 * b,
+    ]
+    .map(|component| component.round().clamp(0.0, 255.0) as u8)
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    match b {
+        0 => a.max(1),
+        _ => gcd(b, a % b),
+    }
+}